@@ -0,0 +1,564 @@
+use serde::Serialize;
+
+/// A string literal delimiter pair
+struct Quote {
+    open: &'static str,
+    close: &'static str,
+    escapes: bool,
+    /// A doubled `close` stands for itself and does not end the literal (C# `@"a ""b"""`)
+    doubled: bool,
+}
+
+/// Comment and string rules for one language family
+pub struct Syntax {
+    line_comments: &'static [&'static str],
+    block_comments: &'static [(&'static str, &'static str)],
+    quotes: &'static [Quote],
+    /// `'` only starts a literal when it looks like a character literal, so
+    /// Rust lifetimes such as `'a` are left alone
+    char_literals: bool,
+    /// Rust raw strings: `r"..."`, `r#"..."#`
+    raw_strings: bool,
+    /// Line comments only start at the beginning of a word (shell `$#`, `${#x}`)
+    comment_at_word_start: bool,
+    /// Block comments nest, as in Rust `/* a /* b */ c */`
+    nested_comments: bool,
+    /// `/.../` after an operator or keyword is a regex literal (JavaScript)
+    regex_literals: bool,
+    /// Shell heredoc bodies (`<<EOF` ... `EOF`) are copied verbatim
+    heredocs: bool,
+}
+
+const DOUBLE: Quote = Quote { open: "\"", close: "\"", escapes: true, doubled: false };
+const SINGLE: Quote = Quote { open: "'", close: "'", escapes: true, doubled: false };
+const BACKTICK: Quote = Quote { open: "`", close: "`", escapes: true, doubled: false };
+const RAW_BACKTICK: Quote = Quote { open: "`", close: "`", escapes: false, doubled: false };
+const VERBATIM: Quote = Quote { open: "@\"", close: "\"", escapes: false, doubled: true };
+const INTERPOLATED_VERBATIM: Quote = Quote { open: "@$\"", close: "\"", escapes: false, doubled: true };
+
+/// An HTML element whose content is shown or run as written
+const fn element(open: &'static str, close: &'static str) -> Quote {
+    Quote { open, close, escapes: false, doubled: false }
+}
+
+static RUST: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comments: &[("/*", "*/")],
+    quotes: &[DOUBLE],
+    char_literals: true,
+    raw_strings: true,
+    comment_at_word_start: false,
+    nested_comments: true,
+    regex_literals: false,
+    heredocs: false,
+};
+
+static C_FAMILY: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comments: &[("/*", "*/")],
+    quotes: &[DOUBLE, RAW_BACKTICK],
+    char_literals: true,
+    raw_strings: false,
+    comment_at_word_start: false,
+    nested_comments: false,
+    regex_literals: false,
+    heredocs: false,
+};
+
+static CSHARP: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comments: &[("/*", "*/")],
+    quotes: &[VERBATIM, INTERPOLATED_VERBATIM, DOUBLE],
+    char_literals: true,
+    raw_strings: false,
+    comment_at_word_start: false,
+    nested_comments: false,
+    regex_literals: false,
+    heredocs: false,
+};
+
+static JAVASCRIPT: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comments: &[("/*", "*/")],
+    quotes: &[DOUBLE, SINGLE, BACKTICK],
+    char_literals: false,
+    raw_strings: false,
+    comment_at_word_start: false,
+    nested_comments: false,
+    regex_literals: true,
+    heredocs: false,
+};
+
+static PYTHON: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comments: &[],
+    quotes: &[
+        Quote { open: "\"\"\"", close: "\"\"\"", escapes: true, doubled: false },
+        Quote { open: "'''", close: "'''", escapes: true, doubled: false },
+        DOUBLE,
+        SINGLE,
+    ],
+    char_literals: false,
+    raw_strings: false,
+    comment_at_word_start: false,
+    nested_comments: false,
+    regex_literals: false,
+    heredocs: false,
+};
+
+static SHELL: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comments: &[],
+    quotes: &[DOUBLE, Quote { open: "'", close: "'", escapes: false, doubled: false }],
+    char_literals: false,
+    raw_strings: false,
+    comment_at_word_start: true,
+    nested_comments: false,
+    regex_literals: false,
+    heredocs: true,
+};
+
+/// `<pre>` and `<textarea>` content is shown as written and `<script>` may
+/// hold template literals, so all three are copied like string literals
+static HTML: Syntax = Syntax {
+    line_comments: &[],
+    block_comments: &[("<!--", "-->")],
+    quotes: &[
+        element("<pre", "</pre>"),
+        element("<PRE", "</PRE>"),
+        element("<textarea", "</textarea>"),
+        element("<TEXTAREA", "</TEXTAREA>"),
+        element("<script", "</script>"),
+        element("<SCRIPT", "</SCRIPT>"),
+    ],
+    char_literals: false,
+    raw_strings: false,
+    comment_at_word_start: false,
+    nested_comments: false,
+    regex_literals: false,
+    heredocs: false,
+};
+
+/// Returns the syntax rules for a lowercase extension (with dot), if supported
+pub fn syntax_for(extension: &str) -> Option<&'static Syntax> {
+    match extension {
+        ".rs" => Some(&RUST),
+        ".c" | ".h" | ".cc" | ".cpp" | ".cxx" | ".hpp" | ".hh" | ".java" | ".go" | ".kt" | ".kts"
+        | ".swift" | ".scala" => Some(&C_FAMILY),
+        ".cs" => Some(&CSHARP),
+        ".js" | ".jsx" | ".mjs" | ".cjs" | ".ts" | ".tsx" | ".mts" | ".cts" => Some(&JAVASCRIPT),
+        ".py" | ".pyw" => Some(&PYTHON),
+        ".sh" | ".bash" | ".zsh" => Some(&SHELL),
+        ".html" | ".htm" => Some(&HTML),
+        _ => None,
+    }
+}
+
+/// Rough token estimate used for reporting savings (about four bytes per token)
+pub fn estimate_tokens(bytes: u64) -> u64 {
+    bytes.div_ceil(4)
+}
+
+/// Bytes compression removed from one file
+#[derive(Serialize)]
+pub struct FileCompression {
+    /// Path relative to the scanned root
+    pub path: String,
+    pub bytes_saved: u64,
+}
+
+impl FileCompression {
+    /// Rough tokens saved, as estimated by [`estimate_tokens`]
+    pub fn tokens_saved(&self) -> u64 {
+        estimate_tokens(self.bytes_saved)
+    }
+}
+
+/// Removes comments, trims trailing whitespace and collapses runs of blank
+/// lines. String literals are copied verbatim, including their line breaks.
+pub fn compress(content: &str, syntax: &Syntax) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut i = 0;
+    // Lines that only held a comment are dropped instead of left blank
+    let mut line_had_comment = false;
+    // Heredocs started on the current line; their bodies follow it
+    let mut heredocs: Vec<(String, bool)> = Vec::new();
+
+    // Keep a shebang line intact
+    if content.starts_with("#!") {
+        i = content.find('\n').unwrap_or(content.len());
+        out.push_str(&content[..i]);
+    }
+
+    while i < content.len() {
+        let rest = &content[i..];
+
+        if syntax.raw_strings {
+            if let Some(len) = raw_string_len(content, i) {
+                out.push_str(&rest[..len]);
+                i += len;
+                continue;
+            }
+        }
+
+        if let Some(quote) = syntax.quotes.iter().find(|q| rest.starts_with(q.open)) {
+            let len = quoted_len(rest, quote);
+            out.push_str(&rest[..len]);
+            i += len;
+            continue;
+        }
+
+        if syntax.char_literals && rest.starts_with('\'') {
+            if let Some(len) = char_literal_len(rest) {
+                out.push_str(&rest[..len]);
+                i += len;
+                continue;
+            }
+        }
+
+        if syntax.regex_literals && rest.starts_with('/') {
+            if let Some(len) = regex_literal_len(content, i) {
+                out.push_str(&rest[..len]);
+                i += len;
+                continue;
+            }
+        }
+
+        let at_word_start = i == 0 || content[..i].ends_with(char::is_whitespace);
+        if (!syntax.comment_at_word_start || at_word_start)
+            && syntax.line_comments.iter().any(|c| rest.starts_with(c))
+        {
+            i += rest.find('\n').unwrap_or(rest.len());
+            line_had_comment = true;
+            continue;
+        }
+
+        if syntax.heredocs {
+            if let Some((len, delimiter, strip_tabs)) = heredoc_start(rest) {
+                out.push_str(&rest[..len]);
+                i += len;
+                heredocs.push((delimiter, strip_tabs));
+                continue;
+            }
+        }
+
+        if let Some((open, close)) = syntax.block_comments.iter().find(|(open, _)| rest.starts_with(open)) {
+            let len = if syntax.nested_comments {
+                nested_comment_len(rest, open, close)
+            } else {
+                rest[open.len()..]
+                    .find(close)
+                    .map(|end| open.len() + end + close.len())
+                    .unwrap_or(rest.len())
+            };
+            i += len;
+            line_had_comment = true;
+
+            // Keep tokens on either side of an inline comment apart, with a
+            // single space between them
+            let next = &content[i..];
+            if out.ends_with([' ', '\t']) {
+                i += next.len() - next.trim_start_matches([' ', '\t']).len();
+            } else if !out.is_empty() && !out.ends_with('\n') && !next.starts_with(char::is_whitespace) {
+                out.push(' ');
+            }
+            continue;
+        }
+
+        let ch = rest.chars().next().unwrap();
+        i += ch.len_utf8();
+        if ch == '\n' {
+            end_line(&mut out, line_had_comment);
+            line_had_comment = false;
+            for (delimiter, strip_tabs) in heredocs.drain(..) {
+                let len = heredoc_body_len(&content[i..], &delimiter, strip_tabs);
+                out.push_str(&content[i..i + len]);
+                i += len;
+            }
+        } else {
+            out.push(ch);
+        }
+    }
+
+    // Trailing whitespace and blank lines at the end of the file
+    let crlf = out.ends_with("\r\n") || content.ends_with("\r\n");
+    out.truncate(out.trim_end().len());
+    if !out.is_empty() && content.ends_with('\n') {
+        out.push_str(if crlf { "\r\n" } else { "\n" });
+    }
+
+    out
+}
+
+/// Finishes the current output line: trims trailing whitespace, then emits the
+/// line break unless it would start a second consecutive blank line
+fn end_line(out: &mut String, line_had_comment: bool) {
+    let crlf = out.ends_with('\r');
+    let trimmed_len = out.trim_end_matches([' ', '\t', '\r']).len();
+    out.truncate(trimmed_len);
+
+    let line_is_empty = out.is_empty() || out.ends_with('\n');
+    if line_is_empty {
+        let previous_blank = out.is_empty() || out.ends_with("\n\n") || out.ends_with("\n\r\n");
+        if line_had_comment || previous_blank {
+            return;
+        }
+    }
+
+    out.push_str(if crlf { "\r\n" } else { "\n" });
+}
+
+/// Length of a quoted literal starting at the beginning of `rest`, including
+/// both delimiters. Unterminated literals run to the end of the input.
+fn quoted_len(rest: &str, quote: &Quote) -> usize {
+    let mut j = quote.open.len();
+    while j < rest.len() {
+        let tail = &rest[j..];
+        if quote.escapes && tail.starts_with('\\') {
+            j += 1 + tail[1..].chars().next().map_or(0, char::len_utf8);
+            continue;
+        }
+        if let Some(after) = tail.strip_prefix(quote.close) {
+            if quote.doubled && after.starts_with(quote.close) {
+                j += 2 * quote.close.len();
+                continue;
+            }
+            return j + quote.close.len();
+        }
+        j += tail.chars().next().map_or(1, char::len_utf8);
+    }
+    rest.len()
+}
+
+/// Length of a block comment that may contain further comments, up to the
+/// `close` matching its `open`
+fn nested_comment_len(rest: &str, open: &str, close: &str) -> usize {
+    let mut depth = 0;
+    let mut j = 0;
+    while j < rest.len() {
+        let tail = &rest[j..];
+        if tail.starts_with(open) {
+            depth += 1;
+            j += open.len();
+        } else if tail.starts_with(close) {
+            depth -= 1;
+            j += close.len();
+            if depth == 0 {
+                return j;
+            }
+        } else {
+            j += tail.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    rest.len()
+}
+
+/// Length of a character literal such as `'a'`, `'\n'` or `'\u{1F600}'`, or
+/// `None` if the quote is something else (a lifetime or label)
+fn char_literal_len(rest: &str) -> Option<usize> {
+    let mut chars = rest.char_indices().skip(1);
+    let (_, first) = chars.next()?;
+    if first == '\\' {
+        // Escapes end at the next quote, which must be close by
+        return rest[2..]
+            .char_indices()
+            .take(10)
+            .find(|&(_, c)| c == '\'')
+            .map(|(end, _)| 2 + end + 1);
+    }
+    match chars.next() {
+        Some((end, '\'')) => Some(end + 1),
+        _ => None,
+    }
+}
+
+/// Keywords after which a `/` starts a regex literal rather than a division
+const REGEX_KEYWORDS: &[&str] = &[
+    "return", "typeof", "instanceof", "case", "do", "else", "in", "of", "void", "yield", "await", "delete",
+    "throw", "new",
+];
+
+/// Length of a JavaScript regex literal such as `/https?:\/\//g` starting at
+/// `start`, or `None` when the slash is a division or starts a comment. A
+/// regex may only follow an operator, an opening bracket or a keyword, and
+/// must close on the same line.
+fn regex_literal_len(content: &str, start: usize) -> Option<usize> {
+    let rest = &content[start..];
+    if rest.starts_with("//") || rest.starts_with("/*") {
+        return None;
+    }
+
+    let before = content[..start].trim_end_matches([' ', '\t']);
+    let after_operator = before
+        .chars()
+        .next_back()
+        .is_none_or(|c| "(,=:[!&|?{};+-*%<>~^\n\r".contains(c));
+    let after_keyword = REGEX_KEYWORDS.iter().any(|keyword| {
+        before.strip_suffix(keyword).is_some_and(|head| {
+            !head
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        })
+    });
+    if !after_operator && !after_keyword {
+        return None;
+    }
+
+    let mut in_class = false;
+    let mut chars = rest.char_indices().skip(1);
+    while let Some((j, c)) = chars.next() {
+        match c {
+            '\\' if chars.next().is_none_or(|(_, escaped)| escaped == '\n') => return None,
+            '\\' => {}
+            '\n' => return None,
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '/' if !in_class => {
+                let flags = rest[j + 1..].len() - rest[j + 1..].trim_start_matches(|c: char| c.is_ascii_alphabetic()).len();
+                return Some(j + 1 + flags);
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parses a heredoc redirection such as `<<EOF`, `<<-'EOF'` or `<< "EOF"` at
+/// the start of `rest`, returning its length, the delimiter and whether the
+/// body's leading tabs are stripped (`<<-`). Here-strings (`<<<`) and
+/// arithmetic shifts (`<< 2`) are not heredocs.
+fn heredoc_start(rest: &str) -> Option<(usize, String, bool)> {
+    let after = rest.strip_prefix("<<")?;
+    if after.starts_with('<') {
+        return None;
+    }
+    let strip_tabs = after.starts_with('-');
+    let after = &after[strip_tabs as usize..];
+    let word = after.trim_start_matches([' ', '\t']);
+    let word_start = rest.len() - word.len();
+
+    let (delimiter, word_len) = match word.chars().next()? {
+        quote @ ('\'' | '"') => {
+            let end = word[1..].find(quote)?;
+            (word[1..1 + end].to_string(), end + 2)
+        }
+        _ => {
+            let escaped = word.starts_with('\\') as usize;
+            let name_len = word[escaped..].len()
+                - word[escaped..]
+                    .trim_start_matches(|c: char| c.is_alphanumeric() || c == '_' || c == '-')
+                    .len();
+            let name = &word[escaped..escaped + name_len];
+            if !name.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+                return None;
+            }
+            (name.to_string(), escaped + name_len)
+        }
+    };
+    Some((word_start + word_len, delimiter, strip_tabs))
+}
+
+/// Length of a heredoc body at the start of `rest`, up to and including the
+/// line holding only `delimiter`; an unterminated body runs to the end
+fn heredoc_body_len(rest: &str, delimiter: &str, strip_tabs: bool) -> usize {
+    let mut j = 0;
+    while j < rest.len() {
+        let end = rest[j..].find('\n').map_or(rest.len(), |n| j + n + 1);
+        let mut line = rest[j..end].trim_end_matches(['\n', '\r']);
+        if strip_tabs {
+            line = line.trim_start_matches('\t');
+        }
+        j = end;
+        if line == delimiter {
+            break;
+        }
+    }
+    j
+}
+
+/// Length of a Rust raw string (`r"..."`, `br#"..."#`) starting at `start`
+fn raw_string_len(content: &str, start: usize) -> Option<usize> {
+    let rest = &content[start..];
+    let prefix = if rest.starts_with("br") {
+        2
+    } else if rest.starts_with('r') {
+        1
+    } else {
+        return None;
+    };
+
+    // `r` must begin a token, not end an identifier like `bar"`
+    let preceded_by_ident = content[..start]
+        .chars()
+        .next_back()
+        .is_some_and(|c| c.is_alphanumeric() || c == '_');
+    if preceded_by_ident {
+        return None;
+    }
+
+    let hashes = rest[prefix..].chars().take_while(|&c| c == '#').count();
+    if !rest[prefix + hashes..].starts_with('"') {
+        return None;
+    }
+
+    let body_start = prefix + hashes + 1;
+    let close = format!("\"{}", "#".repeat(hashes));
+    let len = rest[body_start..]
+        .find(&close)
+        .map(|end| body_start + end + close.len())
+        .unwrap_or(rest.len());
+    Some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_rust_keeps_strings() {
+        let source = "// header\n\nfn main<'a>() {   \n    let s = \"// not a comment\"; // trailing\n\n\n    let c = '\"'; /* inline */ let r = r#\"/* raw */\"#;\n}\n";
+        let compressed = compress(source, &RUST);
+
+        assert_eq!(
+            compressed,
+            "fn main<'a>() {\n    let s = \"// not a comment\";\n\n    let c = '\"'; let r = r#\"/* raw */\"#;\n}\n"
+        );
+
+        let nested = "/* outer /* inner */ still comment */\nfn f() {}\n";
+        assert_eq!(compress(nested, &RUST), "fn f() {}\n");
+    }
+
+    #[test]
+    fn test_compress_shell_and_python() {
+        let shell = "#!/bin/sh\n# comment\necho \"$# args\" ${#x} # done\n";
+        assert_eq!(compress(shell, &SHELL), "#!/bin/sh\necho \"$# args\" ${#x}\n");
+
+        let heredoc = "cat <<-'EOF' > out.txt # note\n# kept   \n\n\n\tEOF\necho $((1 << 2)) # two\n";
+        assert_eq!(
+            compress(heredoc, &SHELL),
+            "cat <<-'EOF' > out.txt\n# kept   \n\n\n\tEOF\necho $((1 << 2))\n"
+        );
+
+        let python = "def f():\n    \"\"\"Doc # kept\n\n\n    \"\"\"\n    return 1  # one\n";
+        assert_eq!(
+            compress(python, &PYTHON),
+            "def f():\n    \"\"\"Doc # kept\n\n\n    \"\"\"\n    return 1\n"
+        );
+    }
+
+    #[test]
+    fn test_compress_other_literals() {
+        let js = "const re = /https?:\\/\\//g; // url\nconst half = a / b; // div\nif (/[/]/.test(s)) {}\n";
+        assert_eq!(
+            compress(js, &JAVASCRIPT),
+            "const re = /https?:\\/\\//g;\nconst half = a / b;\nif (/[/]/.test(s)) {}\n"
+        );
+
+        let cs = "var dir = @\"C:\\temp\\\"; // path\nvar q = @\"say \"\"// hi\"\"\";\n";
+        assert_eq!(compress(cs, &CSHARP), "var dir = @\"C:\\temp\\\";\nvar q = @\"say \"\"// hi\"\"\";\n");
+
+        let html = "<p>a</p>   \n<!-- note -->\n<pre>\n  x   \n\n\n  y\n</pre>\n";
+        assert_eq!(compress(html, &HTML), "<p>a</p>\n<pre>\n  x   \n\n\n  y\n</pre>\n");
+    }
+}
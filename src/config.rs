@@ -1,8 +1,15 @@
-use globset::{Glob, GlobSet, GlobSetBuilder};
-use std::collections::HashSet;
-use std::fs::File;
-use std::io::{BufRead, BufReader, Write};
-use std::path::Path;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone};
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use regex::Regex;
+
+use crate::error::Error;
+use crate::git::GitSelection;
+use crate::normalize::LineEnding;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 const DEFAULT_CONFIG: &str = r#"# FileAssetBuilder Configuration
 # List file extensions to EXCLUDE from processing (one per line)
@@ -122,30 +129,357 @@ obj
 .parcel-cache
 coverage
 .nyc_output
+
+# File and folder names/patterns kept even when hidden (see the hidden
+# setting) or on the built-in OS junk list (Thumbs.db, desktop.ini,
+# .DS_Store, ._*)
+[include]
+# .github
+# .env.example
+
+# General settings (key = value)
+[settings]
+# Files larger than this are skipped or truncated (e.g. 512KB, 2MB, 1048576)
+# max_file_size = 1MB
+# What to do with files over the limit: skip or truncate
+# oversize_action = skip
+# Emit files with identical content once: off, course or run
+# dedupe = off
+# Strip comments and redundant whitespace from Rust, Python, JS/TS,
+# C-family, shell and HTML files: on or off
+# compress = off
+# Replace API keys, private keys, passwords and dotenv values with
+# [REDACTED:<kind>] before writing output: on or off
+# redact_secrets = on
+# Normalize line breaks in file content: keep, lf or crlf
+# line_endings = keep
+# Remove UTF-8 byte order marks from file content: on or off
+# strip_bom = off
+# End every file with exactly one line break: on or off
+# final_newline = off
+# Symbolic links: list them in the directory tree as "name -> target"
+# without content, skip them, or follow them (with loop detection; links
+# pointing outside the scanned directory are never followed)
+# symlinks = list
+# Files and folders whose name starts with a dot: include or exclude
+# hidden = include
+
+# Per-extension override of oversize_action (.ext = skip|truncate)
+[oversize]
+# .log = truncate
+# .json = truncate
+
+# Per-extension override of compress (.ext = on|off)
+[compress]
+# .html = off
+
+# Extra secret detectors (kind = regex); a capture group limits the
+# redaction to the group
+[secret-patterns]
+# internal_token = itk_[A-Za-z0-9]{32}
+
+# Matches of these regexes are never redacted (one per line)
+[secret-allowlist]
+# ^EXAMPLE_
 "#;
 
+/// Source label used for rules that come from the built-in defaults
+const DEFAULT_CONFIG_SOURCE: &str = "<built-in defaults>";
+
+/// Where a config rule was defined
+#[derive(Debug, Clone)]
+pub struct RuleOrigin {
+    /// Config file path, or the flag name for rules given on the command line
+    pub file: String,
+    pub line: Option<usize>,
+}
+
+impl RuleOrigin {
+    pub fn flag(name: &str) -> Self {
+        Self {
+            file: name.to_string(),
+            line: None,
+        }
+    }
+}
+
+impl fmt::Display for RuleOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}", self.file, line),
+            None => write!(f, "{}", self.file),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FolderRule {
+    pub pattern: String,
+    pub origin: RuleOrigin,
+}
+
+/// How far to look for files with identical content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DedupeScope {
+    /// Emit every file in full
+    Off,
+    /// Only dedupe files within the same course
+    Course,
+    /// Dedupe across all courses in the scan
+    Run,
+}
+
+impl DedupeScope {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value.to_ascii_lowercase().as_str() {
+            "off" => Ok(DedupeScope::Off),
+            "course" => Ok(DedupeScope::Course),
+            "run" => Ok(DedupeScope::Run),
+            _ => Err(format!("Invalid dedupe scope '{}' (expected off, course or run)", value)),
+        }
+    }
+}
+
+/// How symbolic links are treated during the walk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SymlinkPolicy {
+    /// Leave links out entirely
+    Skip,
+    /// Walk into linked directories and read linked files, stopping at loops
+    Follow,
+    /// Show links in the directory tree as `name -> target` without content
+    List,
+}
+
+impl SymlinkPolicy {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value.to_ascii_lowercase().as_str() {
+            "skip" => Ok(SymlinkPolicy::Skip),
+            "follow" => Ok(SymlinkPolicy::Follow),
+            "list" => Ok(SymlinkPolicy::List),
+            _ => Err(format!("Invalid symlink policy '{}' (expected skip, follow or list)", value)),
+        }
+    }
+}
+
+/// Whether names starting with a dot are scanned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum HiddenPolicy {
+    Include,
+    Exclude,
+}
+
+impl HiddenPolicy {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value.to_ascii_lowercase().as_str() {
+            "include" => Ok(HiddenPolicy::Include),
+            "exclude" => Ok(HiddenPolicy::Exclude),
+            _ => Err(format!("Invalid hidden policy '{}' (expected include or exclude)", value)),
+        }
+    }
+}
+
+/// Bounds on file modification time; files without a readable time pass
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ModifiedRange {
+    /// Files modified before this are left out
+    pub after: Option<DateTime<Local>>,
+    /// Files modified at or after this are left out
+    pub before: Option<DateTime<Local>>,
+}
+
+impl ModifiedRange {
+    /// Fails when `after` is not earlier than `before`, as no file could match
+    pub fn new(after: Option<DateTime<Local>>, before: Option<DateTime<Local>>) -> Result<Self, String> {
+        if let (Some(after), Some(before)) = (after, before) {
+            if after >= before {
+                return Err(format!(
+                    "--newer-than {} is not earlier than --older-than {}, so no file would be included",
+                    format_cutoff(after),
+                    format_cutoff(before)
+                ));
+            }
+        }
+        Ok(Self { after, before })
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.after.is_some() || self.before.is_some()
+    }
+}
+
+/// Formats a modification time cutoff for headers and reports
+pub fn format_cutoff(cutoff: DateTime<Local>) -> String {
+    cutoff.format("%Y-%m-%d %H:%M:%S %:z").to_string()
+}
+
+/// Course name patterns from `--course` and `--exclude-course`; with no
+/// `--course` patterns every course is selected
+#[derive(Clone)]
+pub struct CourseFilter {
+    selected: GlobSet,
+    selected_rules: Vec<FolderRule>,
+    excluded: GlobSet,
+    excluded_rules: Vec<FolderRule>,
+}
+
+impl Default for CourseFilter {
+    fn default() -> Self {
+        Self {
+            selected: GlobSet::empty(),
+            selected_rules: Vec::new(),
+            excluded: GlobSet::empty(),
+            excluded_rules: Vec::new(),
+        }
+    }
+}
+
+impl CourseFilter {
+    pub fn new(selected: &[String], excluded: &[String]) -> Result<Self, Error> {
+        let patterns = |values: &[String], flag: &str| {
+            values
+                .iter()
+                .map(|value| (value.clone(), RuleOrigin::flag(flag)))
+                .collect::<Vec<_>>()
+        };
+        let (selected, selected_rules) = build_glob_set(patterns(selected, "--course"), "--course")?;
+        let (excluded, excluded_rules) = build_glob_set(patterns(excluded, "--exclude-course"), "--exclude-course")?;
+        Ok(Self {
+            selected,
+            selected_rules,
+            excluded,
+            excluded_rules,
+        })
+    }
+
+    pub fn is_set(&self) -> bool {
+        !self.selected_rules.is_empty() || !self.excluded_rules.is_empty()
+    }
+
+    /// Why the course `name` is left out, or `None` when it is selected
+    pub fn rejection(&self, name: &str) -> Option<String> {
+        if let Some(&i) = self.excluded.matches(name).first() {
+            return Some(format!("excluded by --exclude-course \"{}\"", self.excluded_rules[i].pattern));
+        }
+        (!self.selected_rules.is_empty() && !self.selected.is_match(name))
+            .then(|| "not matched by --course".to_string())
+    }
+}
+
+/// What to do with files larger than `max_file_size`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OversizeAction {
+    Skip,
+    Truncate,
+}
+
+impl OversizeAction {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value.to_ascii_lowercase().as_str() {
+            "skip" => Ok(OversizeAction::Skip),
+            "truncate" => Ok(OversizeAction::Truncate),
+            _ => Err(format!("Invalid oversize action '{}' (expected skip or truncate)", value)),
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum Section {
+    Extensions,
+    Folders,
+    Include,
+    Settings,
+    Oversize,
+    Compress,
+    SecretPatterns,
+    SecretAllowlist,
+    Unknown,
+}
+
+impl Section {
+    fn from_header(header: &str) -> Self {
+        match header.to_ascii_lowercase().as_str() {
+            "[folders]" => Section::Folders,
+            "[include]" => Section::Include,
+            "[settings]" => Section::Settings,
+            "[oversize]" => Section::Oversize,
+            "[compress]" => Section::Compress,
+            "[secret-patterns]" => Section::SecretPatterns,
+            "[secret-allowlist]" => Section::SecretAllowlist,
+            _ => Section::Unknown,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Config {
-    pub excluded_extensions: HashSet<String>,
+    /// Excluded extensions (lowercase, with dot) and the line that declared each
+    pub excluded_extensions: HashMap<String, RuleOrigin>,
     pub excluded_folders: GlobSet,
+    /// Folder rules in the same order as the globs in `excluded_folders`
+    pub folder_rules: Vec<FolderRule>,
+    /// Names from the `[include]` section that are kept even when hidden or junk
+    pub included_names: GlobSet,
+    /// Include rules in the same order as the globs in `included_names`
+    pub include_rules: Vec<FolderRule>,
+    pub hidden: HiddenPolicy,
+    /// Built-in OS junk file names, always dropped unless re-included
+    junk_files: GlobSet,
+    /// Size limit in bytes and where it was set
+    pub max_file_size: Option<(u64, RuleOrigin)>,
+    pub oversize_action: OversizeAction,
+    /// Per-extension oversize actions from the `[oversize]` section
+    pub oversize_overrides: HashMap<String, (OversizeAction, RuleOrigin)>,
+    pub dedupe: DedupeScope,
+    /// Strip comments and redundant whitespace from supported languages
+    pub compress: bool,
+    /// Per-extension compression switches from the `[compress]` section
+    pub compress_overrides: HashMap<String, bool>,
+    /// Replace detected secrets with `[REDACTED:<kind>]`
+    pub redact_secrets: bool,
+    /// Custom secret detectors from `[secret-patterns]` as (kind, regex)
+    pub secret_patterns: Vec<(String, Regex)>,
+    /// Matches that are never redacted, from `[secret-allowlist]`
+    pub secret_allowlist: Vec<Regex>,
+    /// Convert content line breaks to this ending; `None` keeps them as-is
+    pub line_endings: Option<LineEnding>,
+    /// Drop a leading UTF-8 byte order mark from file content
+    pub strip_bom: bool,
+    /// End every file's content with exactly one line break
+    pub final_newline: bool,
+    pub symlinks: SymlinkPolicy,
+    /// Only scan files picked by git (`--git-tracked`, `--git-changed`)
+    pub git_selection: Option<GitSelection>,
+    /// Only scan entries at most this many levels below the root (`--max-depth`)
+    pub max_depth: Option<usize>,
+    /// Only scan files at least this many levels below the root (`--min-depth`)
+    pub min_depth: Option<usize>,
+    /// Only scan these subpaths of the root (`--only`); empty scans everything
+    pub only: Vec<PathBuf>,
+    /// Only scan files modified within this range (`--newer-than`, `--older-than`)
+    pub modified: ModifiedRange,
+    /// Only scan courses whose names pass these patterns
+    pub courses: CourseFilter,
+    /// Record branch, HEAD and each file's last commit in the output
+    pub git_metadata: bool,
+    /// With `git_metadata`, also include uncommitted changes as a diff
+    pub git_diff: bool,
+    /// Section headers that were not recognized; their entries are skipped
+    pub unknown_sections: Vec<(String, RuleOrigin)>,
 }
 
 impl Config {
-    pub fn load(config_path: &Path) -> Self {
+    /// Reads `config_path`, creating it with the default rules first if it
+    /// does not exist. Fails with `Error::Io` when the file cannot be created
+    /// or read, and with `Error::Config` or `Error::InvalidGlob` on the first
+    /// invalid entry.
+    pub fn load(config_path: &Path) -> Result<Self, Error> {
         if !config_path.exists() {
-            println!("Config file not found. Creating default config...");
-            if let Err(e) = Self::create_default(config_path) {
-                eprintln!("Warning: Could not create config file: {}", e);
-                return Self::with_defaults();
-            }
+            Self::create_default(config_path).map_err(|e| Error::io(config_path, e))?;
         }
 
-        match Self::parse_config(config_path) {
-            Ok(config) => config,
-            Err(e) => {
-                eprintln!("Warning: Could not load config: {}. Using defaults.", e);
-                Self::with_defaults()
-            }
-        }
+        let text = fs::read_to_string(config_path).map_err(|e| Error::io(config_path, e))?;
+        Self::parse_str(&text, &config_path.to_string_lossy())
     }
 
     fn create_default(config_path: &Path) -> std::io::Result<()> {
@@ -154,114 +488,390 @@ impl Config {
         Ok(())
     }
 
-    fn parse_config(config_path: &Path) -> std::io::Result<Self> {
-        let file = File::open(config_path)?;
-        let reader = BufReader::new(file);
-        let mut excluded_extensions = HashSet::new();
-        let mut folder_patterns: Vec<String> = Vec::new();
-        let mut in_folders_section = false;
+    /// The built-in default rules, without reading or creating `config.txt`
+    pub fn with_defaults() -> Self {
+        Self::parse_str(DEFAULT_CONFIG, DEFAULT_CONFIG_SOURCE).expect("built-in config is valid")
+    }
 
-        for line in reader.lines() {
-            let line = line?;
+    /// Parses config text; `source` labels the origin of each rule. Unknown
+    /// sections are skipped and listed in `unknown_sections`.
+    pub fn parse_str(text: &str, source: &str) -> Result<Self, Error> {
+        let mut config = Self {
+            excluded_extensions: HashMap::new(),
+            excluded_folders: GlobSet::empty(),
+            folder_rules: Vec::new(),
+            included_names: GlobSet::empty(),
+            include_rules: Vec::new(),
+            hidden: HiddenPolicy::Include,
+            junk_files: build_junk_set(),
+            max_file_size: None,
+            oversize_action: OversizeAction::Skip,
+            oversize_overrides: HashMap::new(),
+            dedupe: DedupeScope::Off,
+            compress: false,
+            compress_overrides: HashMap::new(),
+            redact_secrets: true,
+            secret_patterns: Vec::new(),
+            secret_allowlist: Vec::new(),
+            line_endings: None,
+            strip_bom: false,
+            final_newline: false,
+            symlinks: SymlinkPolicy::List,
+            git_selection: None,
+            max_depth: None,
+            min_depth: None,
+            only: Vec::new(),
+            modified: ModifiedRange::default(),
+            courses: CourseFilter::default(),
+            git_metadata: false,
+            git_diff: false,
+            unknown_sections: Vec::new(),
+        };
+        let mut folder_patterns: Vec<(String, RuleOrigin)> = Vec::new();
+        let mut include_patterns: Vec<(String, RuleOrigin)> = Vec::new();
+        let mut section = Section::Extensions;
+
+        for (index, line) in text.lines().enumerate() {
             let trimmed = line.trim();
+            let origin = RuleOrigin {
+                file: source.to_string(),
+                line: Some(index + 1),
+            };
 
             if trimmed.is_empty() || trimmed.starts_with('#') {
                 continue;
             }
 
-            if trimmed.eq_ignore_ascii_case("[folders]") {
-                in_folders_section = true;
-                continue;
-            }
-
             if trimmed.starts_with('[') && trimmed.ends_with(']') {
-                in_folders_section = false;
+                section = Section::from_header(trimmed);
+                if section == Section::Unknown {
+                    config.unknown_sections.push((trimmed.to_string(), origin));
+                }
                 continue;
             }
 
-            if in_folders_section {
-                folder_patterns.push(trimmed.to_string());
-            } else {
-                if !trimmed.starts_with('.') {
-                    eprintln!(
-                        "Warning: Skipping invalid extension '{}' (must start with '.')",
-                        trimmed
-                    );
-                    continue;
+            let result = match section {
+                Section::Extensions => {
+                    if trimmed.starts_with('.') {
+                        config
+                            .excluded_extensions
+                            .entry(trimmed.to_lowercase())
+                            .or_insert(origin);
+                        Ok(())
+                    } else {
+                        Err(format!("Invalid extension '{}' (must start with '.')", trimmed))
+                    }
+                }
+                Section::Folders => {
+                    folder_patterns.push((trimmed.to_string(), origin));
+                    Ok(())
+                }
+                Section::Include => {
+                    include_patterns.push((trimmed.to_string(), origin));
+                    Ok(())
+                }
+                Section::Settings => split_key_value(trimmed)
+                    .and_then(|(key, value)| config.apply_setting(key, value, origin)),
+                Section::Oversize => split_key_value(trimmed).and_then(|(ext, value)| {
+                    let action = OversizeAction::parse(value)?;
+                    config
+                        .oversize_overrides
+                        .insert(ext.to_lowercase(), (action, origin));
+                    Ok(())
+                }),
+                Section::Compress => split_key_value(trimmed).and_then(|(ext, value)| {
+                    config.compress_overrides.insert(ext.to_lowercase(), parse_bool(value)?);
+                    Ok(())
+                }),
+                Section::SecretPatterns => split_key_value(trimmed).and_then(|(kind, pattern)| {
+                    config.secret_patterns.push((kind.to_string(), parse_regex(pattern)?));
+                    Ok(())
+                }),
+                Section::SecretAllowlist => parse_regex(trimmed).map(|regex| {
+                    config.secret_allowlist.push(regex);
+                }),
+                Section::Unknown => Ok(()),
+            };
+
+            result.map_err(|message| Error::Config {
+                origin: RuleOrigin {
+                    file: source.to_string(),
+                    line: Some(index + 1),
+                },
+                message,
+            })?;
+        }
+
+        let (excluded_folders, folder_rules) = build_glob_set(folder_patterns, source)?;
+        config.excluded_folders = excluded_folders;
+        config.folder_rules = folder_rules;
+        let (included_names, include_rules) = build_glob_set(include_patterns, source)?;
+        config.included_names = included_names;
+        config.include_rules = include_rules;
+        Ok(config)
+    }
+
+    fn apply_setting(&mut self, key: &str, value: &str, origin: RuleOrigin) -> Result<(), String> {
+        match key {
+            "max_file_size" => self.max_file_size = Some((parse_size(value)?, origin)),
+            "oversize_action" => self.oversize_action = OversizeAction::parse(value)?,
+            "dedupe" => self.dedupe = DedupeScope::parse(value)?,
+            "compress" => self.compress = parse_bool(value)?,
+            "redact_secrets" => self.redact_secrets = parse_bool(value)?,
+            "line_endings" => {
+                self.line_endings = match value.to_ascii_lowercase().as_str() {
+                    "keep" => None,
+                    _ => Some(LineEnding::parse(value)?),
                 }
-                excluded_extensions.insert(trimmed.to_lowercase());
             }
+            "strip_bom" => self.strip_bom = parse_bool(value)?,
+            "final_newline" => self.final_newline = parse_bool(value)?,
+            "symlinks" => self.symlinks = SymlinkPolicy::parse(value)?,
+            "hidden" => self.hidden = HiddenPolicy::parse(value)?,
+            _ => return Err(format!("Unknown setting '{}'", key)),
         }
+        Ok(())
+    }
 
-        let excluded_folders = build_glob_set(&folder_patterns);
+    pub fn should_exclude(&self, extension: &str) -> bool {
+        self.excluded_extensions.contains_key(&extension.to_lowercase())
+    }
 
-        println!(
-            "Loaded {} excluded extensions and {} folder patterns from config",
-            excluded_extensions.len(),
-            folder_patterns.len()
-        );
+    /// Returns the origin of the extension entry that excludes `extension`, if any
+    pub fn extension_rule(&self, extension: &str) -> Option<&RuleOrigin> {
+        self.excluded_extensions.get(&extension.to_lowercase())
+    }
 
-        Ok(Self {
-            excluded_extensions,
-            excluded_folders,
-        })
+    /// Returns the first folder rule that matches `dir_name`, if any
+    pub fn folder_rule(&self, dir_name: &str) -> Option<&FolderRule> {
+        self.excluded_folders
+            .matches(dir_name)
+            .first()
+            .map(|&i| &self.folder_rules[i])
     }
 
-    fn with_defaults() -> Self {
-        let mut excluded_extensions = HashSet::new();
-        let mut folder_patterns: Vec<String> = Vec::new();
-        let mut in_folders_section = false;
+    /// Returns the first `[include]` rule that matches `name`, if any
+    pub fn include_rule(&self, name: &str) -> Option<&FolderRule> {
+        self.included_names
+            .matches(name)
+            .first()
+            .map(|&i| &self.include_rules[i])
+    }
 
-        for line in DEFAULT_CONFIG.lines() {
-            let trimmed = line.trim();
+    /// Returns the built-in junk pattern that matches the file `name`, if any
+    pub fn junk_pattern(&self, name: &str) -> Option<&'static str> {
+        self.junk_files.matches(name).first().map(|&i| JUNK_FILES[i])
+    }
 
-            if trimmed.is_empty() || trimmed.starts_with('#') {
-                continue;
-            }
+    /// Size limit in bytes and where it was set
+    pub fn size_limit(&self) -> Option<(u64, &RuleOrigin)> {
+        self.max_file_size.as_ref().map(|(limit, origin)| (*limit, origin))
+    }
 
-            if trimmed.eq_ignore_ascii_case("[folders]") {
-                in_folders_section = true;
-                continue;
-            }
+    /// Oversize handling for a file with `extension`, honoring `[oversize]` overrides
+    pub fn oversize_action_for(&self, extension: &str) -> OversizeAction {
+        self.oversize_overrides
+            .get(&extension.to_lowercase())
+            .map(|(action, _)| *action)
+            .unwrap_or(self.oversize_action)
+    }
 
-            if in_folders_section {
-                folder_patterns.push(trimmed.to_string());
-            } else if trimmed.starts_with('.') {
-                excluded_extensions.insert(trimmed.to_lowercase());
-            }
-        }
+    /// Whether files with `extension` should be compressed, honoring `[compress]` overrides
+    pub fn compress_enabled_for(&self, extension: &str) -> bool {
+        self.compress_overrides
+            .get(&extension.to_lowercase())
+            .copied()
+            .unwrap_or(self.compress)
+    }
 
-        let excluded_folders = build_glob_set(&folder_patterns);
+    /// Returns the first folder pattern that matches `dir_name`, if any
+    pub fn matching_folder_pattern(&self, dir_name: &str) -> Option<&str> {
+        self.folder_rule(dir_name).map(|rule| rule.pattern.as_str())
+    }
+}
 
-        Self {
-            excluded_extensions,
-            excluded_folders,
+fn split_key_value(line: &str) -> Result<(&str, &str), String> {
+    line.split_once('=')
+        .map(|(k, v)| (k.trim(), v.trim()))
+        .ok_or_else(|| format!("Expected 'key = value', got '{}'", line))
+}
+
+fn parse_regex(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "on" | "true" | "yes" => Ok(true),
+        "off" | "false" | "no" => Ok(false),
+        _ => Err(format!("Invalid switch '{}' (expected on or off)", value)),
+    }
+}
+
+/// Parses a modification time cutoff: a date (`2024-05-01`), a local date
+/// and time (`2024-05-01 14:30`, seconds optional), an RFC 3339 timestamp, or
+/// a duration before now (`90s`, `30m`, `12h`, `7d`, `2w`)
+pub fn parse_cutoff(value: &str) -> Result<DateTime<Local>, String> {
+    let value = value.trim();
+    let invalid = || format!("Invalid date or duration '{}' (e.g. 2024-05-01, 2024-05-01 14:30 or 7d)", value);
+
+    if let Some(unit) = value.chars().last().filter(|c| c.is_ascii_alphabetic()) {
+        if let Ok(count) = value[..value.len() - 1].trim().parse::<i64>() {
+            if count < 0 {
+                return Err(format!("Duration '{}' must not be negative", value));
+            }
+            let duration = match unit.to_ascii_lowercase() {
+                's' => Duration::try_seconds(count),
+                'm' => Duration::try_minutes(count),
+                'h' => Duration::try_hours(count),
+                'd' => Duration::try_days(count),
+                'w' => Duration::try_weeks(count),
+                _ => None,
+            };
+            return duration.and_then(|d| Local::now().checked_sub_signed(d)).ok_or_else(invalid);
         }
     }
 
-    pub fn should_exclude(&self, extension: &str) -> bool {
-        self.excluded_extensions.contains(&extension.to_lowercase())
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Local));
     }
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .ok_or_else(invalid)?;
+    Local.from_local_datetime(&naive).earliest().ok_or_else(invalid)
+}
 
-    pub fn should_exclude_dir(&self, dir_name: &str) -> bool {
-        self.excluded_folders.is_match(dir_name)
+/// Parses a byte size such as `1048576`, `512KB` or `2MB` (binary multiples)
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("Invalid size '{}'", value))?;
+    let multiplier = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => return Err(format!("Invalid size unit in '{}' (use B, KB, MB or GB)", value)),
+    };
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("Invalid size '{}' (too large)", value))
+}
+
+/// Files created by operating systems rather than people, matched ignoring case
+const JUNK_FILES: &[&str] = &["Thumbs.db", "desktop.ini", ".DS_Store", "._*"];
+
+fn build_junk_set() -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in JUNK_FILES {
+        builder.add(GlobBuilder::new(pattern).case_insensitive(true).build().expect("valid junk pattern"));
     }
+    builder.build().expect("valid junk patterns")
 }
 
-fn build_glob_set(patterns: &[String]) -> GlobSet {
+/// Builds a name glob set, returning it alongside its rules
+fn build_glob_set(patterns: Vec<(String, RuleOrigin)>, source: &str) -> Result<(GlobSet, Vec<FolderRule>), Error> {
     let mut builder = GlobSetBuilder::new();
-    for pattern in patterns {
-        match Glob::new(pattern) {
+    let mut rules = Vec::new();
+    for (pattern, origin) in patterns {
+        match Glob::new(&pattern) {
             Ok(glob) => {
                 builder.add(glob);
+                rules.push(FolderRule { pattern, origin });
             }
-            Err(e) => {
-                eprintln!("Warning: Invalid folder pattern '{}': {}", pattern, e);
-            }
+            Err(source) => return Err(Error::InvalidGlob { pattern, origin, source }),
         }
     }
-    builder.build().unwrap_or_else(|e| {
-        eprintln!("Warning: Failed to build folder exclusion set: {}", e);
-        GlobSet::empty()
-    })
+    let set = builder.build().map_err(|e| Error::Config {
+        origin: RuleOrigin {
+            file: source.to_string(),
+            line: None,
+        },
+        message: format!("Failed to build pattern set: {}", e.kind()),
+    })?;
+    Ok((set, rules))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_origins() {
+        let text = "# comment\n.log\n\n[folders]\nnode_*\n";
+        let config = Config::parse_str(text, "config.txt").unwrap();
+
+        assert_eq!(config.extension_rule(".LOG").unwrap().line, Some(2));
+        let rule = config.folder_rule("node_modules").unwrap();
+        assert_eq!(rule.pattern, "node_*");
+        assert_eq!(rule.origin.to_string(), "config.txt:5");
+        assert!(config.folder_rule("src").is_none());
+    }
+
+    #[test]
+    fn test_invalid_entries() {
+        let glob = Config::parse_str("[folders]\nnode_[\n", "config.txt");
+        assert!(matches!(glob, Err(Error::InvalidGlob { ref origin, .. }) if origin.line == Some(2)));
+
+        let setting = Config::parse_str(".log\n[settings]\ndedupe = maybe\n", "config.txt");
+        assert!(matches!(setting, Err(Error::Config { ref origin, .. }) if origin.line == Some(3)));
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1048576"), Ok(1048576));
+        assert_eq!(parse_size("512KB"), Ok(512 * 1024));
+        assert_eq!(parse_size("2 mb"), Ok(2 * 1024 * 1024));
+        assert!(parse_size("big").is_err());
+        assert!(parse_size("10XB").is_err());
+        assert!(parse_size("99999999999GB").is_err());
+        assert!(parse_size("99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn test_parse_cutoff() {
+        let date = parse_cutoff("2024-05-01").unwrap();
+        assert_eq!(format_cutoff(date), format_cutoff(parse_cutoff("2024-05-01 00:00").unwrap()));
+        assert!(parse_cutoff("7d").unwrap() < parse_cutoff("1h").unwrap());
+        assert!(parse_cutoff("yesterday").is_err());
+        assert!(parse_cutoff("5y").is_err());
+        assert!(parse_cutoff("-7d").is_err());
+
+        let (week, day) = (parse_cutoff("7d").ok(), parse_cutoff("1d").ok());
+        assert!(ModifiedRange::new(week, day).is_ok());
+        assert!(ModifiedRange::new(day, week).is_err());
+        assert!(ModifiedRange::new(day, day).is_err());
+        assert!(ModifiedRange::new(day, None).is_ok());
+    }
+
+    #[test]
+    fn test_course_filter() {
+        let filter = CourseFilter::new(&["Rust*".to_string()], &["*Old".to_string()]).unwrap();
+        assert!(filter.rejection("Rust Basics").is_none());
+        assert_eq!(filter.rejection("Python").as_deref(), Some("not matched by --course"));
+        assert!(filter.rejection("Rust Old").unwrap().contains("--exclude-course"));
+        assert!(CourseFilter::default().rejection("Python").is_none());
+        assert!(CourseFilter::new(&["[".to_string()], &[]).is_err());
+    }
+
+    #[test]
+    fn test_oversize_overrides() {
+        let text = "[settings]\nmax_file_size = 1KB\noversize_action = skip\n\n[oversize]\n.LOG = truncate\n";
+        let config = Config::parse_str(text, "config.txt").unwrap();
+
+        assert_eq!(config.size_limit().map(|(limit, _)| limit), Some(1024));
+        assert_eq!(config.oversize_action_for(".log"), OversizeAction::Truncate);
+        assert_eq!(config.oversize_action_for(".json"), OversizeAction::Skip);
+    }
 }
@@ -0,0 +1,254 @@
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use similar::TextDiff;
+
+use fileassetbuilder::{
+    read_snapshot, write_output, Config, FileEntry, OutputOptions, PathStyle, ScanResult, Scanner, Snapshot, Truncation,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+impl ChangeKind {
+    fn label(self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Modified => "modified",
+        }
+    }
+}
+
+pub struct FileChange {
+    pub course: String,
+    /// Path below the snapshot root, `/`-separated
+    pub path: String,
+    pub kind: ChangeKind,
+    /// Content before the change; empty for added files
+    pub old: String,
+    /// Content after the change; empty for removed files
+    pub new: String,
+    /// Where the new content leaves out the middle of an oversized file
+    pub truncation: Option<Truncation>,
+}
+
+/// Compares two snapshots file by file, following duplicate references so a
+/// file that became a duplicate only counts as changed if its content did.
+/// Changes are ordered by course, then by path.
+pub fn diff_snapshots(old: &Snapshot, new: &Snapshot) -> Vec<FileChange> {
+    let old_paths: HashSet<&str> = old.files().iter().map(|f| f.relative_path.as_str()).collect();
+    let new_paths: HashSet<&str> = new.files().iter().map(|f| f.relative_path.as_str()).collect();
+    let mut changes = Vec::new();
+
+    for file in old.files() {
+        let old_content = old.content_of(&file.relative_path).unwrap_or_default();
+        match new.content_of(&file.relative_path) {
+            None if !new_paths.contains(file.relative_path.as_str()) => changes.push(FileChange {
+                course: file.course.clone(),
+                path: file.relative_path.clone(),
+                kind: ChangeKind::Removed,
+                old: old_content.to_string(),
+                new: String::new(),
+                truncation: None,
+            }),
+            new_content => {
+                let new_content = new_content.unwrap_or_default();
+                if new_content != old_content {
+                    changes.push(FileChange {
+                        course: file.course.clone(),
+                        path: file.relative_path.clone(),
+                        kind: ChangeKind::Modified,
+                        old: old_content.to_string(),
+                        new: new_content.to_string(),
+                        truncation: new.resolve(&file.relative_path).and_then(|f| f.truncation),
+                    });
+                }
+            }
+        }
+    }
+
+    for file in new.files() {
+        if !old_paths.contains(file.relative_path.as_str()) {
+            changes.push(FileChange {
+                course: file.course.clone(),
+                path: file.relative_path.clone(),
+                kind: ChangeKind::Added,
+                old: String::new(),
+                new: new.content_of(&file.relative_path).unwrap_or_default().to_string(),
+                truncation: new.resolve(&file.relative_path).and_then(|f| f.truncation),
+            });
+        }
+    }
+
+    changes.sort_by(|a, b| {
+        a.course
+            .cmp(&b.course)
+            .then_with(|| a.path.split('/').cmp(b.path.split('/')))
+    });
+    changes
+}
+
+/// Per-course summary of changed files, followed by unified diffs unless
+/// `summary_only` is set
+pub fn format_text(changes: &[FileChange], summary_only: bool) -> String {
+    let mut out = String::new();
+
+    if changes.is_empty() {
+        out.push_str("No changes");
+        return out;
+    }
+
+    let mut start = 0;
+    while start < changes.len() {
+        let course = &changes[start].course;
+        let end = start + changes[start..].iter().take_while(|c| &c.course == course).count();
+        let group = &changes[start..end];
+        let count = |kind| group.iter().filter(|c| c.kind == kind).count();
+
+        writeln!(
+            out,
+            "{}: {} added, {} removed, {} modified",
+            course,
+            count(ChangeKind::Added),
+            count(ChangeKind::Removed),
+            count(ChangeKind::Modified)
+        )
+        .unwrap();
+        for change in group {
+            writeln!(out, "  {:<9} {}", change.kind.label(), change.path).unwrap();
+        }
+        out.push('\n');
+        start = end;
+    }
+
+    if !summary_only {
+        for change in changes {
+            let (old_header, new_header) = match change.kind {
+                ChangeKind::Added => ("/dev/null".to_string(), format!("b/{}", change.path)),
+                ChangeKind::Removed => (format!("a/{}", change.path), "/dev/null".to_string()),
+                ChangeKind::Modified => (format!("a/{}", change.path), format!("b/{}", change.path)),
+            };
+            let diff = TextDiff::from_lines(&change.old, &change.new);
+            write!(out, "{}", diff.unified_diff().header(&old_header, &new_header)).unwrap();
+            if !out.ends_with('\n') {
+                out.push('\n');
+            }
+        }
+    }
+
+    out.truncate(out.trim_end().len());
+    out
+}
+
+/// A scan result holding the new content of every added or modified file,
+/// ready to be written as a "changes only" snapshot
+pub fn changes_as_scan_result(changes: &[FileChange]) -> ScanResult {
+    let files = changes
+        .iter()
+        .filter(|c| c.kind != ChangeKind::Removed)
+        .map(|c| FileEntry {
+            relative_path: c.path.clone(),
+            content: c.new.clone(),
+            duplicate_of: None,
+            truncation: c.truncation,
+        })
+        .collect();
+    ScanResult::from_files(files)
+}
+
+/// Runs the `diff` command: `old` and `new` are snapshot files or directories
+/// to scan. Exits the process on errors.
+pub fn run(config: &Config, old: &Path, new: &Path, summary: bool, changes_only: Option<&Path>, output: &str) {
+    let load = |path: &Path| {
+        if path.is_dir() {
+            Scanner::new(path)
+                .config(config.clone())
+                .output_filename(output)
+                .scan()
+                .map(Snapshot::from_scan)
+        } else {
+            read_snapshot(path)
+        }
+    };
+
+    let changes = match load(old).and_then(|old| Ok((old, load(new)?))) {
+        Ok((old, new)) => diff_snapshots(&old, &new),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    println!("{}", format_text(&changes, summary));
+
+    if let Some(path) = changes_only {
+        let options = OutputOptions {
+            path_style: PathStyle::Relative,
+            ..Default::default()
+        };
+        if let Err(e) = write_output(path, &changes_as_scan_result(&changes), &options) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        println!("\nChanged files written to: {:?}", path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fileassetbuilder::SnapshotFile;
+
+    fn snapshot(files: &[(&str, &str, Option<&str>)]) -> Snapshot {
+        Snapshot::new(
+            files
+                .iter()
+                .map(|&(path, content, duplicate_of)| SnapshotFile {
+                    course: path.split('/').next().unwrap().to_string(),
+                    header_path: path.to_string(),
+                    relative_path: path.to_string(),
+                    content: content.to_string(),
+                    duplicate_of: duplicate_of.map(str::to_string),
+                    truncation: None,
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_diff_snapshots() {
+        let old = snapshot(&[
+            ("Rust/main.rs", "fn main() {}\n", None),
+            ("Rust/old.rs", "gone\n", None),
+            ("Rust/copy.rs", "", Some("Rust/main.rs")),
+            ("Go/main.go", "package main\n", None),
+        ]);
+        let new = snapshot(&[
+            ("Rust/main.rs", "fn main() { run() }\n", None),
+            ("Rust/copy.rs", "fn main() {}\n", None),
+            ("Go/main.go", "package main\n", None),
+            ("Go/util.go", "package util\n", None),
+        ]);
+
+        let changes = diff_snapshots(&old, &new);
+        let summary: Vec<(&str, ChangeKind)> = changes.iter().map(|c| (c.path.as_str(), c.kind)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Go/util.go", ChangeKind::Added),
+                ("Rust/main.rs", ChangeKind::Modified),
+                ("Rust/old.rs", ChangeKind::Removed),
+            ]
+        );
+
+        let text = format_text(&changes, false);
+        assert!(text.starts_with("Go: 1 added, 0 removed, 0 modified\n  added     Go/util.go\n"));
+        assert!(text.contains("--- a/Rust/main.rs\n+++ b/Rust/main.rs\n@@ -1 +1 @@\n-fn main() {}\n+fn main() { run() }"));
+    }
+}
@@ -0,0 +1,200 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+use serde::Serialize;
+
+use fileassetbuilder::{Config, Decision, Error, OutputOptions, PathFormatter, Scanner};
+
+#[derive(Serialize)]
+pub struct DryRunReport {
+    pub root: String,
+    pub entries: Vec<DryRunEntry>,
+    pub summary: DryRunSummary,
+}
+
+#[derive(Serialize)]
+pub struct DryRunEntry {
+    pub path: String,
+    pub kind: &'static str,
+    pub decision: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub size: u64,
+}
+
+#[derive(Serialize, Default)]
+pub struct DryRunSummary {
+    pub included: usize,
+    pub excluded_by_extension: usize,
+    pub excluded_by_folder: usize,
+    pub binary: usize,
+    pub too_large: usize,
+    pub truncated: usize,
+    pub not_selected: usize,
+    pub other_courses: usize,
+    pub skipped: usize,
+    pub hidden: usize,
+    pub junk: usize,
+    pub too_old: usize,
+    pub too_new: usize,
+    pub symlinks: usize,
+    pub repeated: usize,
+    pub included_bytes: u64,
+}
+
+/// Walks `root` exactly like a real scan and records the decision for every
+/// candidate path, without reading more than a small head of each file.
+pub fn plan_directory(
+    root: &Path,
+    config: &Config,
+    output_filename: &str,
+    options: &OutputOptions,
+) -> Result<DryRunReport, Error> {
+    let plan = Scanner::new(root)
+        .config(config.clone())
+        .output_filename(output_filename)
+        .plan()?;
+
+    let paths = PathFormatter::new(&plan.root, options);
+    let mut entries = Vec::new();
+    let mut summary = DryRunSummary::default();
+
+    for entry in plan.entries {
+        match entry.decision {
+            Decision::Included => {
+                summary.included += 1;
+                summary.included_bytes += entry.size;
+            }
+            Decision::ExcludedExtension(_) => summary.excluded_by_extension += 1,
+            Decision::ExcludedFolder(_) => summary.excluded_by_folder += 1,
+            Decision::Binary => summary.binary += 1,
+            Decision::TooLarge(_) => summary.too_large += 1,
+            Decision::NotSelected(_) => summary.not_selected += 1,
+            Decision::OtherCourse(_) => summary.other_courses += 1,
+            Decision::Unreadable(_) => summary.skipped += 1,
+            Decision::Hidden => summary.hidden += 1,
+            Decision::Junk(_) => summary.junk += 1,
+            Decision::TooOld(_) => summary.too_old += 1,
+            Decision::TooNew(_) => summary.too_new += 1,
+            Decision::Symlink(_) => summary.symlinks += 1,
+            Decision::SameFile(_) => summary.repeated += 1,
+            Decision::Truncated(limit) => {
+                summary.truncated += 1;
+                summary.included_bytes += limit;
+            }
+        }
+
+        entries.push(DryRunEntry {
+            path: paths.format(&entry.relative_path),
+            kind: if entry.is_dir { "folder" } else { "file" },
+            decision: entry.decision.label(),
+            reason: entry.decision.reason(),
+            size: entry.size,
+        });
+    }
+
+    Ok(DryRunReport {
+        root: paths.root().to_string(),
+        entries,
+        summary,
+    })
+}
+
+pub fn format_text(report: &DryRunReport) -> String {
+    let mut out = String::new();
+
+    for entry in &report.entries {
+        let label = format!("[{}]", entry.decision);
+        let path = if entry.kind == "folder" {
+            format!("{}/", entry.path)
+        } else {
+            entry.path.clone()
+        };
+        match &entry.reason {
+            Some(reason) => writeln!(out, "{:<14} {} ({})", label, path, reason),
+            None => writeln!(out, "{:<14} {} ({} bytes)", label, path, entry.size),
+        }
+        .unwrap();
+    }
+
+    let s = &report.summary;
+    writeln!(out, "\nSummary:").unwrap();
+    writeln!(out, "- Included: {} ({} bytes)", s.included, s.included_bytes).unwrap();
+    writeln!(out, "- Excluded by extension: {}", s.excluded_by_extension).unwrap();
+    writeln!(out, "- Excluded by folder pattern: {}", s.excluded_by_folder).unwrap();
+    writeln!(out, "- Binary: {}", s.binary).unwrap();
+    writeln!(out, "- Too large (skipped): {}", s.too_large).unwrap();
+    write!(out, "- Too large (truncated): {}", s.truncated).unwrap();
+    if s.not_selected > 0 {
+        write!(out, "\n- Not selected: {}", s.not_selected).unwrap();
+    }
+    if s.other_courses > 0 {
+        write!(out, "\n- Courses not selected: {}", s.other_courses).unwrap();
+    }
+    if s.hidden > 0 {
+        write!(out, "\n- Hidden: {}", s.hidden).unwrap();
+    }
+    if s.junk > 0 {
+        write!(out, "\n- OS junk files: {}", s.junk).unwrap();
+    }
+    if s.too_old > 0 {
+        write!(out, "\n- Modified too long ago: {}", s.too_old).unwrap();
+    }
+    if s.too_new > 0 {
+        write!(out, "\n- Modified too recently: {}", s.too_new).unwrap();
+    }
+    if s.symlinks > 0 {
+        write!(out, "\n- Symbolic links not followed: {}", s.symlinks).unwrap();
+    }
+    if s.repeated > 0 {
+        write!(out, "\n- Reached again through a link: {}", s.repeated).unwrap();
+    }
+    if s.skipped > 0 {
+        write!(out, "\n- Skipped (could not be read): {}", s.skipped).unwrap();
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_plan_directory_summary() {
+        let root = std::env::temp_dir().join(format!("fileassetbuilder-dry-run-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("Rust/build")).unwrap();
+        fs::write(root.join("Rust/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("Rust/notes.md"), "# Notes\n").unwrap();
+        fs::write(root.join("Rust/big.txt"), "x".repeat(100)).unwrap();
+        fs::write(root.join("Rust/run.log"), "ok\n").unwrap();
+        fs::write(root.join("Rust/build/out.txt"), "built\n").unwrap();
+        // A text extension does not keep a file with NUL bytes in
+        fs::write(root.join("Rust/data.txt"), b"\x00\x01\x02binary").unwrap();
+
+        let text = ".log\n\n[folders]\nbuild\n\n[settings]\nmax_file_size = 50\noversize_action = truncate\n";
+        let config = Config::parse_str(text, "config.txt").unwrap();
+        let report = plan_directory(&root, &config, "out.txt", &OutputOptions::default()).unwrap();
+
+        let decision = |name: &str| {
+            let entry = report.entries.iter().find(|e| e.path.ends_with(name)).unwrap();
+            (entry.decision, entry.kind)
+        };
+        assert_eq!(decision("data.txt"), ("binary", "file"));
+        assert_eq!(decision("build"), ("excluded", "folder"));
+
+        let s = &report.summary;
+        assert_eq!(s.included, 2);
+        assert_eq!(s.truncated, 1);
+        assert_eq!(s.binary, 1);
+        assert_eq!(s.excluded_by_extension, 1);
+        assert_eq!(s.excluded_by_folder, 1);
+        // Included files count in full and truncated ones up to the limit
+        assert_eq!(s.included_bytes, 13 + 8 + 50);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
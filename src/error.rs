@@ -0,0 +1,85 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::config::RuleOrigin;
+
+/// Everything that can stop a scan, a config load or writing the output
+#[derive(Debug)]
+pub enum Error {
+    /// A file or directory could not be created, read or resolved
+    Io { path: PathBuf, source: io::Error },
+    /// Writing the output failed, e.g. because the disk is full
+    Write {
+        /// Output file; `None` when rendering to a writer of the caller's
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+    /// A config entry could not be parsed
+    Config { origin: RuleOrigin, message: String },
+    /// A `[folders]` or `[include]` pattern is not a valid glob
+    InvalidGlob {
+        pattern: String,
+        origin: RuleOrigin,
+        source: globset::Error,
+    },
+    /// A git command could not be started or exited with an error
+    Git {
+        /// Arguments after `git`
+        command: String,
+        /// Exit code; `None` if git could not be started or was killed
+        status: Option<i32>,
+        /// What git printed on stderr, or why it could not be started
+        message: String,
+    },
+    /// The worker pool for reading files could not be started
+    ThreadPool(rayon::ThreadPoolBuildError),
+    /// A file given as a snapshot is not in the output format
+    InvalidSnapshot { path: PathBuf, message: String },
+    /// A path given on the command line cannot be used, e.g. because it is
+    /// outside the scan root
+    InvalidPath { path: PathBuf, message: String },
+}
+
+impl Error {
+    pub(crate) fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Error::Io {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Write { path: Some(path), source } => write!(f, "Failed to write {}: {}", path.display(), source),
+            Error::Write { path: None, source } => write!(f, "Failed to write output: {}", source),
+            Error::Config { origin, message } => write!(f, "{} ({})", message, origin),
+            Error::InvalidGlob { pattern, origin, source } => {
+                write!(f, "Invalid pattern '{}' ({}): {}", pattern, origin, source.kind())
+            }
+            Error::Git {
+                command,
+                status: Some(code),
+                message,
+            } => write!(f, "git {} failed with exit code {}: {}", command, code, message),
+            Error::Git { command, message, .. } => write!(f, "git {}: {}", command, message),
+            Error::ThreadPool(source) => write!(f, "Failed to create thread pool: {}", source),
+            Error::InvalidSnapshot { path, message } => write!(f, "{}: not a valid snapshot: {}", path.display(), message),
+            Error::InvalidPath { path, message } => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } | Error::Write { source, .. } => Some(source),
+            Error::InvalidGlob { source, .. } => Some(source),
+            Error::ThreadPool(source) => Some(source),
+            Error::Config { .. } | Error::Git { .. } | Error::InvalidSnapshot { .. } | Error::InvalidPath { .. } => None,
+        }
+    }
+}
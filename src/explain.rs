@@ -0,0 +1,428 @@
+use std::fmt;
+use std::fs;
+use std::path::{self, Component, Path, PathBuf};
+
+use crate::config::{Config, RuleOrigin, SymlinkPolicy};
+use crate::error::Error;
+use crate::git::GitFiles;
+use crate::scanner::{
+    extension_of, hidden_decision, link_target, modified_decision, size_decision, sniff_binary, Decision,
+};
+
+/// One rule evaluated against the path or one of its ancestors
+pub struct Check {
+    pub subject: String,
+    pub rule: &'static str,
+    pub matched: Option<String>,
+    pub origin: Option<RuleOrigin>,
+}
+
+/// The rules that decide whether a path is scanned, in the order a scan
+/// applies them; displays as the report of the `explain` command
+pub struct Explanation {
+    pub path: PathBuf,
+    pub root: PathBuf,
+    pub checks: Vec<Check>,
+    pub decision: Decision,
+    pub origin: Option<RuleOrigin>,
+}
+
+/// Evaluates the scan rules for `path` relative to `root`, stopping at the
+/// first rule that excludes it, the same way a scan would.
+pub fn explain_path(path: &Path, root: &Path, config: &Config, output_filename: &str) -> Result<Explanation, Error> {
+    let root = root.canonicalize().map_err(|e| Error::io(root, e))?;
+    // Links inside the root are judged by the symlinks policy below, so the
+    // path itself is only made absolute, not resolved
+    let path = lexical_absolute(path).map_err(|e| Error::io(path, e))?;
+    fs::symlink_metadata(&path).map_err(|e| Error::io(&path, e))?;
+
+    let relative = path
+        .strip_prefix(&root)
+        .ok()
+        .or_else(|| {
+            let written_root = lexical_absolute(root.as_path()).ok()?;
+            path.strip_prefix(written_root).ok()
+        })
+        .ok_or_else(|| Error::InvalidPath {
+            path: path.clone(),
+            message: format!("not inside the scan root {}", root.display()),
+        })?
+        .to_path_buf();
+    let path = root.join(&relative);
+
+    let is_dir = path.is_dir();
+    let mut checks = Vec::new();
+
+    if config.max_depth.is_some() || config.min_depth.is_some() || !config.only.is_empty() {
+        let reason = scope_reason(config, &relative, is_dir);
+        checks.push(Check {
+            subject: relative.display().to_string(),
+            rule: "scope",
+            matched: reason.clone(),
+            origin: None,
+        });
+        if let Some(reason) = reason {
+            return Ok(Explanation {
+                path,
+                root,
+                checks,
+                decision: Decision::NotSelected(reason),
+                origin: None,
+            });
+        }
+    }
+
+    let course = relative.iter().next().map(|name| name.to_string_lossy());
+    if let Some(course) = course.filter(|_| config.courses.is_set()) {
+        let reason = config.courses.rejection(&course);
+        checks.push(Check {
+            subject: course.to_string(),
+            rule: "course selection",
+            matched: reason.clone(),
+            origin: None,
+        });
+        if let Some(reason) = reason {
+            return Ok(Explanation {
+                path,
+                root,
+                checks,
+                decision: Decision::OtherCourse(reason),
+                origin: None,
+            });
+        }
+    }
+
+    // Every level below the root is checked for links, and every directory is
+    // matched against [folders], including the path itself when it is one
+    let components: Vec<_> = relative.components().collect();
+    let dir_count = if is_dir { components.len() } else { components.len().saturating_sub(1) };
+    let mut current = PathBuf::new();
+    for (index, component) in components.iter().enumerate() {
+        current.push(component);
+        if let Some(decision) = check_link(config, &root, &current, &mut checks) {
+            return Ok(Explanation {
+                path,
+                root,
+                checks,
+                decision,
+                origin: None,
+            });
+        }
+        if index >= dir_count {
+            break;
+        }
+        let name = component.as_os_str().to_string_lossy();
+        let rule = config.folder_rule(&name);
+        checks.push(Check {
+            subject: format!("{}/", current.display()),
+            rule: "[folders] pattern",
+            matched: rule.map(|r| r.pattern.clone()),
+            origin: rule.map(|r| r.origin.clone()),
+        });
+        if let Some(rule) = rule {
+            return Ok(Explanation {
+                decision: Decision::ExcludedFolder(rule.pattern.clone()),
+                origin: Some(rule.origin.clone()),
+                path,
+                root,
+                checks,
+            });
+        }
+        let subject = format!("{}/", current.display());
+        if let Some(decision) = check_hidden(config, &name, true, subject, &mut checks) {
+            return Ok(Explanation {
+                path,
+                root,
+                checks,
+                decision,
+                origin: None,
+            });
+        }
+    }
+
+    if is_dir {
+        return Ok(Explanation {
+            path,
+            root,
+            checks,
+            decision: Decision::Included,
+            origin: None,
+        });
+    }
+
+    if path == root.join(output_filename) {
+        return Err(Error::InvalidPath {
+            path,
+            message: "the output file is never scanned".to_string(),
+        });
+    }
+
+    if let Some(selection) = &config.git_selection {
+        let git_files = GitFiles::load(&root, selection)?;
+        let selected = git_files.contains(&relative.to_string_lossy());
+        checks.push(Check {
+            subject: relative.display().to_string(),
+            rule: "git selection",
+            matched: (!selected).then(|| git_files.reason().to_string()),
+            origin: None,
+        });
+        if !selected {
+            return Ok(Explanation {
+                path,
+                root,
+                checks,
+                decision: Decision::NotSelected(git_files.reason().to_string()),
+                origin: None,
+            });
+        }
+    }
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    if let Some(decision) = check_hidden(config, &name, false, relative.display().to_string(), &mut checks) {
+        return Ok(Explanation {
+            path,
+            root,
+            checks,
+            decision,
+            origin: None,
+        });
+    }
+
+    let extension = extension_of(&path);
+    let ext_origin = config.extension_rule(&extension).cloned();
+    checks.push(Check {
+        subject: relative.display().to_string(),
+        rule: "extension entry",
+        matched: ext_origin.as_ref().map(|_| extension.clone()),
+        origin: ext_origin.clone(),
+    });
+    if ext_origin.is_some() {
+        return Ok(Explanation {
+            path,
+            root,
+            checks,
+            decision: Decision::ExcludedExtension(extension),
+            origin: ext_origin,
+        });
+    }
+
+    let metadata = path.metadata().ok();
+    if config.modified.is_set() {
+        let modified = metadata.as_ref().and_then(|m| m.modified().ok());
+        let decision = modified_decision(&config.modified, modified);
+        checks.push(Check {
+            subject: relative.display().to_string(),
+            rule: "modified time",
+            matched: decision.as_ref().and_then(Decision::reason),
+            origin: None,
+        });
+        if let Some(decision) = decision {
+            return Ok(Explanation {
+                path,
+                root,
+                checks,
+                decision,
+                origin: None,
+            });
+        }
+    }
+
+    let size = metadata.map(|m| m.len()).unwrap_or(0);
+    let size_checked = size_decision(config, &extension, size);
+    if let Some((limit, origin)) = config.size_limit() {
+        checks.push(Check {
+            subject: relative.display().to_string(),
+            rule: "size limit",
+            matched: (size > limit).then(|| format!("{} bytes > {}", size, limit)),
+            origin: Some(origin.clone()),
+        });
+        if let Decision::TooLarge(_) = size_checked {
+            return Ok(Explanation {
+                path,
+                root,
+                checks,
+                decision: size_checked,
+                origin: Some(origin.clone()),
+            });
+        }
+    }
+
+    let binary = sniff_binary(&path).map_err(|e| Error::io(&path, e))?;
+    checks.push(Check {
+        subject: relative.display().to_string(),
+        rule: "binary sniff",
+        matched: binary.then(|| "NUL byte in file head".to_string()),
+        origin: None,
+    });
+
+    Ok(Explanation {
+        path,
+        root,
+        checks,
+        decision: if binary { Decision::Binary } else { size_checked },
+        origin: None,
+    })
+}
+
+/// Why `relative` is outside the `--only`, `--max-depth` and `--min-depth` scope
+fn scope_reason(config: &Config, relative: &Path, is_dir: bool) -> Option<String> {
+    let depth = relative.components().count();
+    let in_only = config.only.is_empty()
+        || config
+            .only
+            .iter()
+            .any(|only| relative.starts_with(only) || (is_dir && only.starts_with(relative)));
+
+    if !in_only {
+        return Some("outside the --only paths".to_string());
+    }
+    if let Some(max) = config.max_depth.filter(|&max| depth > max) {
+        return Some(format!("deeper than --max-depth {}", max));
+    }
+    config
+        .min_depth
+        .filter(|&min| !is_dir && depth < min)
+        .map(|min| format!("shallower than --min-depth {}", min))
+}
+
+/// Records whether `name` is left out as hidden or OS junk, or kept by an
+/// `[include]` pattern, and returns the decision when it is left out
+/// Applies the `symlinks` policy when `relative` is a link, as the walk does:
+/// unfollowed links are listed without content and followed ones must stay
+/// inside the root
+fn check_link(config: &Config, root: &Path, relative: &Path, checks: &mut Vec<Check>) -> Option<Decision> {
+    let path = root.join(relative);
+    if !fs::symlink_metadata(&path).is_ok_and(|m| m.is_symlink()) {
+        return None;
+    }
+
+    let decision = if fs::metadata(&path).is_err() {
+        Some(Decision::Unreadable("broken symlink".to_string()))
+    } else if config.symlinks != SymlinkPolicy::Follow {
+        Some(Decision::Symlink(link_target(&path)))
+    } else if !fs::canonicalize(&path).is_ok_and(|real| real.starts_with(root)) {
+        Some(Decision::Unreadable("link points outside the root".to_string()))
+    } else {
+        None
+    };
+    checks.push(Check {
+        subject: relative.display().to_string(),
+        rule: "symbolic link",
+        matched: decision.as_ref().and_then(Decision::reason),
+        origin: None,
+    });
+    decision
+}
+
+/// `path` made absolute with `.` and `..` resolved by name, without following links
+fn lexical_absolute(path: &Path) -> std::io::Result<PathBuf> {
+    let mut resolved = PathBuf::new();
+    for component in path::absolute(path)?.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    Ok(resolved)
+}
+
+fn check_hidden(config: &Config, name: &str, is_dir: bool, subject: String, checks: &mut Vec<Check>) -> Option<Decision> {
+    if let Some(rule) = config.include_rule(name) {
+        checks.push(Check {
+            subject,
+            rule: "[include] pattern",
+            matched: Some(rule.pattern.clone()),
+            origin: Some(rule.origin.clone()),
+        });
+        return None;
+    }
+
+    let decision = hidden_decision(config, name, is_dir);
+    checks.push(Check {
+        subject,
+        rule: "hidden/junk name",
+        matched: decision.as_ref().and_then(Decision::reason),
+        origin: None,
+    });
+    decision
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Path: {:?}", self.path)?;
+        writeln!(f, "Root: {:?}\n", self.root)?;
+
+        for check in &self.checks {
+            let result = match (&check.matched, &check.origin) {
+                (Some(m), Some(origin)) => format!("matches \"{}\" ({})", m, origin),
+                (Some(m), None) => format!("matches ({})", m),
+                (None, _) => "no match".to_string(),
+            };
+            writeln!(f, "  {:<18} {}: {}", check.rule, check.subject, result)?;
+        }
+
+        let reason = self.decision.reason().unwrap_or_default();
+        let verdict = match (&self.decision, &self.origin) {
+            (Decision::Included, _) => "INCLUDED".to_string(),
+            (Decision::Truncated(_), _) => format!("INCLUDED ({})", reason),
+            (Decision::NotSelected(_) | Decision::OtherCourse(_), _) => format!("NOT SELECTED ({})", reason),
+            (_, Some(origin)) => format!("EXCLUDED by {} at {}", reason, origin),
+            (_, None) => format!("EXCLUDED by {}", reason),
+        };
+        write!(f, "\nResult: {}", verdict)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HiddenPolicy;
+    use crate::scanner::walk_entries;
+
+    #[test]
+    fn test_explain_matches_walk() {
+        let root = std::env::temp_dir().join(format!("fileassetbuilder-explain-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("Rust/build/debug")).unwrap();
+        fs::create_dir_all(root.join("Rust/.drafts")).unwrap();
+        fs::write(root.join("Rust/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("Rust/run.log"), "ok\n").unwrap();
+        fs::write(root.join("Rust/build/debug/out.txt"), "built\n").unwrap();
+        fs::write(root.join("Rust/.drafts/idea.md"), "idea\n").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("main.rs", root.join("Rust/link.rs")).unwrap();
+        let root = root.canonicalize().unwrap();
+
+        let mut config = Config::parse_str(".log\n\n[folders]\nbuild\n", "config.txt").unwrap();
+        config.hidden = HiddenPolicy::Exclude;
+        let entries = walk_entries(&root, &config, &root.join("out.txt")).unwrap();
+        // A path inside an excluded folder is reported at that folder
+        let walked = |relative: &str| {
+            let mut path = Path::new(relative);
+            loop {
+                if let Some(entry) = entries.iter().find(|e| Path::new(&e.relative_path) == path) {
+                    return entry.decision.clone();
+                }
+                path = path.parent().expect("an ancestor is walked");
+            }
+        };
+
+        let mut expected = vec![
+            ("Rust/main.rs", Decision::Included),
+            ("Rust/run.log", Decision::ExcludedExtension(".log".to_string())),
+            ("Rust/build/debug/out.txt", Decision::ExcludedFolder("build".to_string())),
+            ("Rust/.drafts/idea.md", Decision::Hidden),
+        ];
+        expected.extend(cfg!(unix).then(|| ("Rust/link.rs", Decision::Symlink("main.rs".to_string()))));
+        for (relative, decision) in expected {
+            let explanation = explain_path(&root.join(relative), &root, &config, "out.txt").unwrap();
+            assert_eq!(explanation.decision, decision, "{}", relative);
+            assert_eq!(walked(relative), decision, "{}", relative);
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,338 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::process::Command;
+
+use crate::error::Error;
+
+/// Restricts a scan to files chosen by the git repository containing the root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitSelection {
+    /// Files in the index
+    Tracked,
+    /// Files that differ from `base` in the working tree, plus untracked files
+    /// that are not ignored. With `context`, every other file in the same
+    /// directories is included too.
+    Changed { base: String, context: bool },
+}
+
+/// The files a `GitSelection` picked, relative to the scanned root
+pub struct GitFiles {
+    files: HashSet<String>,
+    /// Directories whose files are all included as context
+    context_dirs: HashSet<String>,
+    reason: String,
+}
+
+impl GitFiles {
+    pub fn load(root: &Path, selection: &GitSelection) -> Result<Self, Error> {
+        match selection {
+            GitSelection::Tracked => Ok(Self {
+                files: git_paths(root, &["ls-files", "-z", "--cached"])?,
+                context_dirs: HashSet::new(),
+                reason: "not tracked by git".to_string(),
+            }),
+            GitSelection::Changed { base, context } => {
+                let commit = format!("{}^{{commit}}", base);
+                git_paths(root, &["rev-parse", "--verify", "--quiet", &commit]).map_err(|e| match e {
+                    Error::Git { command, status: Some(code), .. } => Error::Git {
+                        command,
+                        status: Some(code),
+                        message: format!("unknown revision '{}'", base),
+                    },
+                    e => e,
+                })?;
+
+                let mut files = git_paths(root, &["diff", "--name-only", "-z", "--relative", base, "--"])?;
+                files.extend(git_paths(root, &["ls-files", "-z", "--others", "--exclude-standard"])?);
+
+                let context_dirs = if *context {
+                    files.iter().map(|f| parent_of(f).to_string()).collect()
+                } else {
+                    HashSet::new()
+                };
+
+                Ok(Self {
+                    files,
+                    context_dirs,
+                    reason: format!("unchanged since {}", base),
+                })
+            }
+        }
+    }
+
+    /// `relative` may use either path separator
+    pub fn contains(&self, relative: &str) -> bool {
+        let relative = relative.replace('\\', "/");
+        self.files.contains(&relative) || self.context_dirs.contains(parent_of(&relative))
+    }
+
+    /// Why files outside the selection are left out
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+fn parent_of(relative: &str) -> &str {
+    relative.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+/// Marks the start of a commit in `git log` output, ahead of any file name
+const COMMIT_MARKER: char = '\u{1}';
+
+/// The last commit that touched a file
+pub struct CommitInfo {
+    pub hash: String,
+    pub date: String,
+    pub author: String,
+}
+
+/// State of the repository containing the scanned root
+pub struct RepoInfo {
+    /// Current branch, or `HEAD` when detached
+    pub branch: String,
+    pub head: String,
+    pub subject: String,
+    /// Paths with staged, unstaged or untracked changes
+    pub changed_files: usize,
+    /// Uncommitted changes against `HEAD` per file, when requested
+    pub diff: Option<Vec<FileDiff>>,
+    /// Last commit per file, keyed by `/`-separated path relative to the root
+    last_commits: HashMap<String, CommitInfo>,
+}
+
+/// The uncommitted changes to one file, as printed by `git diff`
+pub struct FileDiff {
+    /// `/`-separated path relative to the root
+    pub path: String,
+    pub text: String,
+}
+
+/// Paths passed to one `git diff` or `git log` call, keeping the command
+/// line short
+const PATHS_PER_CALL: usize = 200;
+
+impl RepoInfo {
+    /// Looks up the last commit of each of `paths`, relative to `root`, and
+    /// with `diff` their uncommitted changes. Other files are never asked
+    /// about, so the history walk only covers the files in the output.
+    pub fn load(root: &Path, paths: &[String], diff: bool) -> Result<Self, Error> {
+        let branch = git_output(root, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+        let head = git_output(root, &["log", "-1", "--format=%H%x00%s"])?;
+        let (head, subject) = head.trim_end().split_once('\0').unwrap_or((head.trim_end(), ""));
+        let status = git_output(root, &["status", "--porcelain", "-z", "--", "."])?;
+
+        let diff = if diff { Some(file_diffs(root, paths)?) } else { None };
+
+        Ok(Self {
+            branch: branch.trim().to_string(),
+            head: head.to_string(),
+            subject: subject.to_string(),
+            changed_files: count_status_entries(&status),
+            diff,
+            last_commits: last_commits(root, paths)?,
+        })
+    }
+
+    /// `relative` may use either path separator
+    pub fn last_commit(&self, relative: &str) -> Option<&CommitInfo> {
+        self.last_commits.get(&relative.replace('\\', "/"))
+    }
+}
+
+/// Entries in `git status --porcelain -z` output; renames and copies are
+/// followed by an extra entry holding the original path
+fn count_status_entries(status: &str) -> usize {
+    let mut entries = status.split('\0').filter(|e| !e.is_empty());
+    let mut count = 0;
+    while let Some(entry) = entries.next() {
+        count += 1;
+        if entry.starts_with(['R', 'C']) {
+            entries.next();
+        }
+    }
+    count
+}
+
+/// Diffs `paths` against `HEAD`, split into one entry per changed file. The
+/// paths are passed as literal pathspecs so none of them is read as a glob.
+fn file_diffs(root: &Path, paths: &[String]) -> Result<Vec<FileDiff>, Error> {
+    let mut diffs = Vec::new();
+    for chunk in paths.chunks(PATHS_PER_CALL) {
+        let mut args = vec!["-c", "core.quotePath=false", "diff", "HEAD", "--relative", "--no-color", "--no-ext-diff", "--"];
+        let pathspecs = literal_pathspecs(chunk);
+        args.extend(pathspecs.iter().map(String::as_str));
+
+        let output = git_output(root, &args)?;
+        let mut rest = output.as_str();
+        while !rest.is_empty() {
+            let end = rest[1..].find("\ndiff --git ").map_or(rest.len(), |i| i + 2);
+            let (text, next) = rest.split_at(end);
+            diffs.push(FileDiff {
+                path: diff_path(text).unwrap_or_default().to_string(),
+                text: text.to_string(),
+            });
+            rest = next;
+        }
+    }
+    Ok(diffs)
+}
+
+/// The path in a `diff --git a/<path> b/<path>` header, which is written
+/// twice so it can be split off even when it contains spaces
+fn diff_path(text: &str) -> Option<&str> {
+    let header = text.lines().next()?.strip_prefix("diff --git a/")?;
+    let path = header.get(..header.len().checked_sub(3)? / 2)?;
+    (header == format!("{} b/{}", path, path)).then_some(path)
+}
+
+/// Walks the history of `paths`, newest first, keeping the first commit seen
+/// for each of them
+fn last_commits(root: &Path, paths: &[String]) -> Result<HashMap<String, CommitInfo>, Error> {
+    let format = format!("--format={}%h%x00%ad%x00%an", COMMIT_MARKER);
+    let mut commits = HashMap::new();
+    for chunk in paths.chunks(PATHS_PER_CALL) {
+        let mut args = vec!["-c", "core.quotePath=false", "log", &format, "--date=short", "--name-only", "--relative", "--"];
+        let pathspecs = literal_pathspecs(chunk);
+        args.extend(pathspecs.iter().map(String::as_str));
+        let log = git_output(root, &args)?;
+        collect_last_commits(&log, &mut commits);
+    }
+    Ok(commits)
+}
+
+/// Adds the files listed under each commit of a `git log --name-only` output
+/// that no newer commit touched
+fn collect_last_commits(log: &str, commits: &mut HashMap<String, CommitInfo>) {
+    let mut current: Option<(&str, &str, &str)> = None;
+    for line in log.lines() {
+        if let Some(meta) = line.strip_prefix(COMMIT_MARKER) {
+            let mut fields = meta.splitn(3, '\0');
+            current = Some((
+                fields.next().unwrap_or_default(),
+                fields.next().unwrap_or_default(),
+                fields.next().unwrap_or_default(),
+            ));
+        } else if let (Some((hash, date, author)), false) = (current, line.is_empty()) {
+            commits.entry(line.to_string()).or_insert_with(|| CommitInfo {
+                hash: hash.to_string(),
+                date: date.to_string(),
+                author: author.to_string(),
+            });
+        }
+    }
+}
+
+/// Pathspecs matching exactly `paths`, so none of them is read as a glob
+fn literal_pathspecs(paths: &[String]) -> Vec<String> {
+    paths.iter().map(|p| format!(":(literal){}", p.replace('\\', "/"))).collect()
+}
+
+/// Runs git in `root` and returns its NUL-separated output. Paths printed by
+/// `ls-files` and `diff --relative` are relative to `root`.
+fn git_paths(root: &Path, args: &[&str]) -> Result<HashSet<String>, Error> {
+    Ok(git_output(root, args)?
+        .split('\0')
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Every git query runs the `git` command-line tool instead of linking a git
+/// implementation, so the crate builds without native dependencies and
+/// honours the user's git config; the git options fail with a clear error
+/// when `git` is not on `PATH`.
+fn git_output(root: &Path, args: &[&str]) -> Result<String, Error> {
+    let git_error = |status, message| Error::Git {
+        command: args.join(" "),
+        status,
+        message,
+    };
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => git_error(
+                None,
+                "the git options need the git command-line tool, which was not found on PATH".to_string(),
+            ),
+            _ => git_error(None, format!("failed to run git: {}", e)),
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(git_error(output.status.code(), stderr.trim().to_string()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(items: &[&str]) -> HashSet<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_git_files_contains() {
+        let git_files = GitFiles {
+            files: set(&["C/changed.txt", "top.txt"]),
+            context_dirs: set(&["C", ""]),
+            reason: String::new(),
+        };
+
+        assert!(git_files.contains("C/changed.txt"));
+        assert!(git_files.contains("C\\changed.txt"));
+        // Other files in a changed file's folder come along as context
+        assert!(git_files.contains("C/other.txt"));
+        assert!(git_files.contains("C\\other.txt"));
+        assert!(git_files.contains("readme.md"));
+        // Context does not reach into subfolders or folders with a similar name
+        assert!(!git_files.contains("C/sub/deep.txt"));
+        assert!(!git_files.contains("CC/other.txt"));
+
+        let no_context = GitFiles { context_dirs: HashSet::new(), ..git_files };
+        assert!(no_context.contains("top.txt"));
+        assert!(!no_context.contains("C/other.txt"));
+        assert!(!no_context.contains("readme.md"));
+    }
+
+    #[test]
+    fn test_count_status_entries() {
+        assert_eq!(count_status_entries(""), 0);
+        assert_eq!(count_status_entries(" M a.txt\0?? b.txt\0"), 2);
+        // The original path after a rename or copy is not a separate change
+        assert_eq!(count_status_entries("R  new.txt\0old.txt\0 M a.txt\0"), 2);
+        assert_eq!(count_status_entries("C  copy.txt\0src.txt\0"), 1);
+        assert_eq!(count_status_entries("A  with space.txt\0"), 1);
+    }
+
+    #[test]
+    fn test_diff_path() {
+        assert_eq!(diff_path("diff --git a/C/a.txt b/C/a.txt\nindex 1..2\n"), Some("C/a.txt"));
+        assert_eq!(diff_path("diff --git a/my file b/x.txt b/my file b/x.txt\n"), Some("my file b/x.txt"));
+        // A rename has two different paths and no single one to report
+        assert_eq!(diff_path("diff --git a/old.txt b/new.txt\n"), None);
+        assert_eq!(diff_path("index 1..2\n"), None);
+        assert_eq!(diff_path("diff --git a/"), None);
+    }
+
+    #[test]
+    fn test_collect_last_commits() {
+        let log = format!(
+            "{m}bbb\02024-02-01\0Bo\n\nC/a.txt\n{m}aaa\02024-01-01\0Al\n\nC/a.txt\nC/b.txt\n",
+            m = COMMIT_MARKER
+        );
+        let mut commits = HashMap::new();
+        collect_last_commits(&log, &mut commits);
+
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits["C/a.txt"].hash, "bbb");
+        assert_eq!(commits["C/a.txt"].author, "Bo");
+        assert_eq!(commits["C/b.txt"].hash, "aaa");
+        assert_eq!(commits["C/b.txt"].date, "2024-01-01");
+    }
+}
@@ -0,0 +1,45 @@
+//! Consolidates a directory tree of course material or code into a single
+//! text snapshot.
+//!
+//! ```no_run
+//! use fileassetbuilder::{render, Config, OutputOptions, Scanner};
+//!
+//! let result = Scanner::new("courses").config(Config::with_defaults()).scan()?;
+//! render(std::io::stdout().lock(), &result, &OutputOptions::default())?;
+//! # Ok::<(), fileassetbuilder::Error>(())
+//! ```
+//!
+//! [`Scanner::plan`] classifies paths without reading their contents,
+//! [`explain_path`] reports the rule that decides a single path, and
+//! [`read_snapshot`] parses a written snapshot back into its files.
+
+mod compress;
+mod config;
+mod error;
+mod explain;
+mod git;
+mod normalize;
+mod output;
+mod scanner;
+mod secrets;
+mod snapshot;
+
+pub use compress::{estimate_tokens, FileCompression};
+pub use config::{
+    parse_cutoff, parse_size, Config, CourseFilter, DedupeScope, FolderRule, HiddenPolicy, ModifiedRange,
+    OversizeAction, RuleOrigin, SymlinkPolicy,
+};
+pub use error::Error;
+pub use explain::{explain_path, Check, Explanation};
+pub use git::{CommitInfo, FileDiff, GitSelection, RepoInfo};
+pub use normalize::LineEnding;
+pub use output::{render, write_output, OutputOptions, PathFormatter, PathStyle, Timestamp};
+pub use scanner::{
+    CourseGroup, Decision, FileEntry, ScanCache, ScanPlan, ScanResult, ScanStats, Scanner, SkippedEntry, Truncation,
+    WalkEntry,
+};
+pub use secrets::{FileRedactions, Redaction};
+pub use snapshot::{read_snapshot, Snapshot, SnapshotFile};
+
+/// Output filename written to each input directory and skipped while scanning
+pub const DEFAULT_OUTPUT_FILENAME: &str = "fileassets.txt";
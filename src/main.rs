@@ -1,28 +1,45 @@
-mod config;
-mod output;
-mod scanner;
+mod diff;
+mod dry_run;
+mod unpack;
+mod watch;
 
+use std::collections::HashMap;
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use clap::Parser;
+use chrono::{DateTime, Local};
+use clap::{Parser, Subcommand};
 
-use config::Config;
-use output::write_output;
-use scanner::scan_directory;
+use fileassetbuilder::{
+    estimate_tokens, explain_path, parse_cutoff, parse_size, write_output, Config, CourseFilter, DedupeScope, Error,
+    FileCompression, FileRedactions, GitSelection, HiddenPolicy, LineEnding, ModifiedRange, OutputOptions, OversizeAction,
+    PathFormatter, PathStyle, RuleOrigin, ScanCache, Scanner, SkippedEntry, SymlinkPolicy, Timestamp,
+    DEFAULT_OUTPUT_FILENAME,
+};
+
+use crate::dry_run::plan_directory;
 
-const DEFAULT_OUTPUT_FILENAME: &str = "fileassets.txt";
 const CONFIG_FILENAME: &str = "config.txt";
 
 #[derive(Parser)]
 #[command(name = "fileassetsbuildercourse")]
 #[command(about = "Consolidate directory files into a single output file")]
 #[command(version)]
+#[command(subcommand_negates_reqs = true)]
 #[command(after_help = "EXAMPLES:\n  \
     fileassetsbuildercourse -i \"[C:\\project]\"\n  \
     fileassetsbuildercourse -i \"[C:\\project1 C:\\project2]\"\n  \
-    fileassetsbuildercourse -i \"[C:\\project]\" -o snapshot.txt")]
+    fileassetsbuildercourse -i \"[C:\\project]\" -o snapshot.txt\n  \
+    fileassetsbuildercourse -i \"[C:\\project]\" --dry-run\n  \
+    fileassetsbuildercourse -i \"[C:\\project]\" --watch\n  \
+    fileassetsbuildercourse -i \"[C:\\project]\" --git-changed main --git-context\n  \
+    fileassetsbuildercourse explain C:\\project\\node_modules\\lib.js --root C:\\project\n  \
+    fileassetsbuildercourse unpack snapshot.txt --into C:\\restored\n  \
+    fileassetsbuildercourse diff old.txt C:\\project --changes-only changes.txt")]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Input directories in bracket syntax: "[dir1 dir2 dir3]"
     #[arg(short, long, required = true, value_parser = parse_bracket_input, num_args = 1..)]
     input: Vec<Vec<PathBuf>>,
@@ -34,6 +51,196 @@ struct Args {
     /// Verbose output
     #[arg(short, long, default_value = "false")]
     verbose: bool,
+
+    /// Skip or truncate files larger than this (e.g. 512KB, 2MB); overrides max_file_size in config
+    #[arg(long, global = true, value_parser = parse_size)]
+    max_file_size: Option<u64>,
+
+    /// What to do with files over the size limit; overrides oversize_action in config
+    #[arg(long, global = true, value_enum)]
+    oversize_action: Option<OversizeAction>,
+
+    /// Only include files tracked in the git index
+    #[arg(long, global = true, default_value = "false", conflicts_with = "git_changed")]
+    git_tracked: bool,
+
+    /// Only include files changed since a git commit or branch, plus untracked files
+    #[arg(long, global = true, value_name = "REF")]
+    git_changed: Option<String>,
+
+    /// With --git-changed, also include the other files in each changed file's folder
+    #[arg(long, global = true, default_value = "false", requires = "git_changed")]
+    git_context: bool,
+
+    /// Only scan entries at most this many levels below the input directory
+    #[arg(long, global = true, value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// Only scan files at least this many levels below the input directory
+    #[arg(long, global = true, value_name = "N")]
+    min_depth: Option<usize>,
+
+    /// Only scan this path relative to the input directory; repeatable
+    #[arg(long, global = true, value_name = "SUBPATH")]
+    only: Vec<PathBuf>,
+
+    /// Only scan courses whose name matches this glob (e.g. "Rust*"); repeatable
+    #[arg(long, global = true, value_name = "GLOB")]
+    course: Vec<String>,
+
+    /// Leave out courses whose name matches this glob; repeatable
+    #[arg(long, global = true, value_name = "GLOB")]
+    exclude_course: Vec<String>,
+
+    /// Only include files modified since a date (2024-05-01, "2024-05-01 14:30") or duration ago (12h, 7d, 2w)
+    #[arg(long, global = true, value_name = "DATE|DURATION", value_parser = parse_cutoff)]
+    newer_than: Option<DateTime<Local>>,
+
+    /// Only include files last modified before a date or duration ago
+    #[arg(long, global = true, value_name = "DATE|DURATION", value_parser = parse_cutoff)]
+    older_than: Option<DateTime<Local>>,
+
+    /// Add branch, HEAD and dirty status, and each file's last commit, to the output
+    #[arg(long, default_value = "false")]
+    git_info: bool,
+
+    /// With --git-info, also include uncommitted changes as a diff against HEAD
+    #[arg(long, default_value = "false", requires = "git_info")]
+    git_diff: bool,
+
+    /// Emit files with identical content once; overrides dedupe in config
+    #[arg(long, value_enum)]
+    dedupe: Option<DedupeScope>,
+
+    /// Strip comments and redundant whitespace from supported languages
+    #[arg(long, default_value = "false")]
+    compress: bool,
+
+    /// Abort without writing output if any secret is detected
+    #[arg(long, default_value = "false")]
+    fail_on_secrets: bool,
+
+    /// Scan or skip files and folders whose name starts with a dot; overrides hidden in config
+    #[arg(long, value_enum)]
+    hidden: Option<HiddenPolicy>,
+
+    /// How symbolic links are treated; overrides symlinks in config
+    #[arg(long, value_enum)]
+    symlinks: Option<SymlinkPolicy>,
+
+    /// Abort without writing output if any directory or file could not be read
+    #[arg(long, default_value = "false")]
+    strict: bool,
+
+    /// Append a "Skipped entries" section listing paths that could not be read
+    #[arg(long, default_value = "false")]
+    list_skipped: bool,
+
+    /// Leave out the table of contents after the directory list
+    #[arg(long, default_value = "false")]
+    no_toc: bool,
+
+    /// Prefix each line of file content with its line number
+    #[arg(long, default_value = "false")]
+    line_numbers: bool,
+
+    /// Show line count and byte size in each file header
+    #[arg(long, default_value = "false")]
+    file_stats: bool,
+
+    /// Convert line breaks in file content; overrides line_endings in config
+    #[arg(long, value_enum, conflicts_with = "reproducible")]
+    line_endings: Option<LineEnding>,
+
+    /// Remove UTF-8 byte order marks from file content
+    #[arg(long, default_value = "false")]
+    strip_bom: bool,
+
+    /// End every file's content with exactly one line break
+    #[arg(long, default_value = "false")]
+    final_newline: bool,
+
+    /// Line ending of the output file itself
+    #[arg(long, value_enum, default_value_t = LineEnding::Lf)]
+    output_line_endings: LineEnding,
+
+    /// How file paths are shown in headers and reports
+    #[arg(long, value_enum, default_value_t = PathStyle::Absolute, conflicts_with = "reproducible")]
+    path_style: PathStyle,
+
+    /// Byte-identical output across runs and platforms: relative paths with /,
+    /// LF line breaks without BOMs in file content, and a timestamp from
+    /// SOURCE_DATE_EPOCH or none at all
+    #[arg(long, default_value = "false")]
+    reproducible: bool,
+
+    /// Replace the home directory with ~ in absolute paths
+    #[arg(long, default_value = "false")]
+    redact_home: bool,
+
+    /// List every candidate path and why it would be included or excluded, without writing output
+    #[arg(long, default_value = "false")]
+    dry_run: bool,
+
+    /// Keep running and regenerate the output whenever files in the input directories change
+    #[arg(long, default_value = "false", conflicts_with = "dry_run")]
+    watch: bool,
+
+    /// Print the dry-run report as JSON
+    #[arg(long, default_value = "false", requires = "dry_run")]
+    json: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Report which config rule includes or excludes a single path
+    Explain {
+        /// File or directory to explain
+        path: PathBuf,
+
+        /// Scan root the path is evaluated against [default: current directory]
+        #[arg(short, long)]
+        root: Option<PathBuf>,
+
+        /// Output filename the scan would skip
+        #[arg(short, long, default_value = DEFAULT_OUTPUT_FILENAME)]
+        output: String,
+    },
+
+    /// Recreate the files in a snapshot below a directory
+    Unpack {
+        /// Snapshot file written by a previous run
+        snapshot: PathBuf,
+
+        /// Directory to restore into; created if missing
+        #[arg(long)]
+        into: PathBuf,
+
+        /// Replace existing files whose content differs
+        #[arg(long, default_value = "false")]
+        force: bool,
+    },
+
+    /// Compare two snapshots, or a snapshot against a directory
+    Diff {
+        /// Earlier snapshot file or directory
+        old: PathBuf,
+
+        /// Later snapshot file or directory
+        new: PathBuf,
+
+        /// List changed files per course without the unified diffs
+        #[arg(long, default_value = "false")]
+        summary: bool,
+
+        /// Also write a snapshot containing only the added and modified files
+        #[arg(long)]
+        changes_only: Option<PathBuf>,
+
+        /// Output filename to skip when scanning a directory
+        #[arg(short, long, default_value = DEFAULT_OUTPUT_FILENAME)]
+        output: String,
+    },
 }
 
 /// Parse bracket-enclosed space-separated input: [dir1 dir2 dir3]
@@ -61,16 +268,36 @@ fn parse_bracket_input(s: &str) -> Result<Vec<PathBuf>, String> {
 fn main() {
     let args = Args::parse();
 
-    // Flatten input paths from bracket syntax
-    let input_dirs: Vec<PathBuf> = args.input.into_iter().flatten().collect();
+    let config = load_config(&args);
 
-    let config_path = get_config_path();
-    let config = Config::load(&config_path);
+    if let Some(Command::Explain { path, root, output }) = &args.command {
+        run_explain(&config, path, root.as_deref(), output);
+        return;
+    }
+
+    if let Some(Command::Unpack { snapshot, into, force }) = &args.command {
+        unpack::run(snapshot, into, *force);
+        return;
+    }
+
+    if let Some(Command::Diff { old, new, summary, changes_only, output }) = &args.command {
+        diff::run(&config, old, new, *summary, changes_only.as_deref(), output);
+        return;
+    }
+
+    // Flatten input paths from bracket syntax
+    let output_options = build_output_options(&args);
+    let input_dirs: Vec<PathBuf> = args.input.iter().flatten().cloned().collect();
 
     let mut has_errors = false;
+    let mut dry_run_reports = Vec::new();
+    let mut watched = Vec::new();
+    let mut caches: HashMap<PathBuf, ScanCache> = HashMap::new();
 
     for input_directory in &input_dirs {
-        println!("{}", "=".repeat(60));
+        if !args.json {
+            println!("{}", "=".repeat(60));
+        }
 
         if !input_directory.exists() {
             eprintln!("Error: Input directory does not exist: {:?}", input_directory);
@@ -93,9 +320,27 @@ fn main() {
             }
         };
 
+        if args.dry_run {
+            match plan_directory(&input_dir, &config, &args.output, &output_options) {
+                Ok(report) if args.json => dry_run_reports.push(report),
+                Ok(report) => {
+                    println!("Dry run for: {:?}\n", input_dir);
+                    println!("{}", dry_run::format_text(&report));
+                }
+                Err(e) => {
+                    eprintln!("Error scanning directory: {}", e);
+                    has_errors = true;
+                }
+            }
+            continue;
+        }
+
         let output_path = input_dir.join(&args.output);
 
-        if output_path.exists() {
+        // Watch mode owns the output file and rewrites it on every change
+        if args.watch {
+            watched.push(input_dir.clone());
+        } else if output_path.exists() {
             println!("Output file already exists: {:?}", output_path);
             println!("Skipping scan and file creation.");
             continue;
@@ -105,12 +350,53 @@ fn main() {
         println!("Input directory: {:?}", input_dir);
         println!("Output file: {:?}\n", output_path);
 
-        match scan_directory(&input_dir, &config, &args.output, args.verbose) {
+        let scanner = Scanner::new(&input_dir)
+            .config(config.clone())
+            .output_filename(&args.output)
+            .verbose(args.verbose)
+            .show_progress(true)
+            .detect_secrets(args.fail_on_secrets);
+
+        // Watch mode keeps the files read here so rebuilds only read changes
+        let scanned = if args.watch {
+            scanner.scan_cached(caches.entry(input_dir.clone()).or_default())
+        } else {
+            scanner.scan()
+        };
+        match scanned {
             Ok(result) => {
+                if !result.skipped.is_empty() {
+                    print_skipped(&result.skipped, &PathFormatter::new(&result.root, &output_options));
+                }
+                if !result.read_errors.is_empty() {
+                    print_read_errors(&result.read_errors);
+                }
+                if args.strict && result.is_incomplete() {
+                    eprintln!("Error: Some entries could not be read; not writing {:?} (--strict)", output_path);
+                    has_errors = true;
+                    continue;
+                }
+                if !result.redactions.is_empty() {
+                    print_redactions(
+                        &result.redactions,
+                        config.redact_secrets,
+                        &PathFormatter::new(&result.root, &output_options),
+                    );
+                    if args.fail_on_secrets {
+                        eprintln!("Error: Secrets detected; not writing {:?} (--fail-on-secrets)", output_path);
+                        has_errors = true;
+                        continue;
+                    }
+                }
+
+                if args.verbose && !result.compressions.is_empty() {
+                    print_compressions(&result.compressions, &PathFormatter::new(&result.root, &output_options));
+                }
+
                 println!("\nWriting output file...");
 
-                if let Err(e) = write_output(&output_path, &result) {
-                    eprintln!("Error writing output: {}", e);
+                if let Err(e) = write_output(&output_path, &result, &output_options) {
+                    eprintln!("Error: {}", e);
                     has_errors = true;
                     continue;
                 }
@@ -121,6 +407,44 @@ fn main() {
                 println!("- Total files found: {}", result.stats.total_files);
                 println!("- Files excluded by config: {}", result.stats.files_excluded);
                 println!("- Folders skipped: {}", result.stats.folders_skipped);
+                println!("- Binary files skipped: {}", result.stats.files_binary);
+                println!("- Files skipped as too large: {}", result.stats.files_too_large);
+                println!("- Files truncated: {}", result.stats.files_truncated);
+                println!("- Unreadable files: {}", result.stats.files_unreadable);
+                println!("- Entries skipped by walk errors: {}", result.stats.entries_skipped);
+                println!("- Hidden entries skipped: {}", result.stats.hidden_skipped);
+                println!("- OS junk files skipped: {}", result.stats.junk_skipped);
+                if config.modified.is_set() {
+                    println!("- Files modified too long ago: {}", result.stats.files_too_old);
+                    println!("- Files modified too recently: {}", result.stats.files_too_new);
+                }
+                if config.symlinks != SymlinkPolicy::Follow {
+                    println!("- Symbolic links not followed: {}", result.stats.symlinks);
+                }
+                if result.stats.files_repeated > 0 {
+                    println!("- Files reached again through links: {}", result.stats.files_repeated);
+                }
+                if config.courses.is_set() {
+                    println!("- Courses not selected: {}", result.stats.courses_skipped);
+                }
+                if config.git_selection.is_some() || config.max_depth.is_some() || config.min_depth.is_some() {
+                    println!("- Entries not selected: {}", result.stats.files_not_selected);
+                }
+                println!(
+                    "- Duplicate files: {} ({} bytes saved)",
+                    result.stats.files_deduplicated, result.stats.bytes_saved
+                );
+                println!(
+                    "- Secrets {}: {} in {} files",
+                    if config.redact_secrets { "redacted" } else { "detected" },
+                    result.stats.secrets_redacted,
+                    result.stats.files_with_secrets
+                );
+                println!(
+                    "- Compression saved: {} bytes (~{} tokens)",
+                    result.stats.compressed_bytes_saved,
+                    estimate_tokens(result.stats.compressed_bytes_saved)
+                );
                 println!("- Files processed: {}", result.stats.files_processed);
                 println!("- Workers used: {}", result.stats.worker_count);
                 println!("Output written to: {:?}", output_path);
@@ -132,11 +456,204 @@ fn main() {
         }
     }
 
+    if !watched.is_empty() {
+        println!("\nWatching {} director{} for changes (Ctrl+C to stop)...", watched.len(), if watched.len() == 1 { "y" } else { "ies" });
+        let result = watch::watch(&watched, &args.output, |root, changed| {
+            let cache = caches.entry(root.to_path_buf()).or_default();
+            watch::rebuild(root, changed, cache, &config, &args, &output_options)
+        });
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            has_errors = true;
+        }
+    }
+
+    if args.json {
+        match serde_json::to_string_pretty(&dry_run_reports) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Error serializing dry-run report: {}", e);
+                has_errors = true;
+            }
+        }
+    }
+
     if has_errors {
         std::process::exit(1);
     }
 }
 
+fn build_output_options(args: &Args) -> OutputOptions {
+    let mut options = OutputOptions {
+        line_numbers: args.line_numbers,
+        file_stats: args.file_stats,
+        path_style: args.path_style,
+        redact_home: args.redact_home,
+        line_ending: args.output_line_endings,
+        skipped_entries: args.list_skipped,
+        table_of_contents: !args.no_toc,
+        ..Default::default()
+    };
+
+    if args.reproducible {
+        options.path_style = PathStyle::Relative;
+        options.forward_slashes = true;
+        options.utc = true;
+        options.timestamp = match env::var("SOURCE_DATE_EPOCH").ok().and_then(|v| v.trim().parse().ok()) {
+            Some(secs) => Timestamp::Epoch(secs),
+            None => Timestamp::Omit,
+        };
+    }
+
+    options
+}
+
+fn print_skipped(skipped: &[SkippedEntry], paths: &PathFormatter) {
+    eprintln!("\nSkipped entries:");
+    for entry in skipped {
+        let suffix = if entry.is_dir { "/" } else { "" };
+        eprintln!("- {}{}: {}", paths.format(&entry.relative_path), suffix, entry.reason);
+    }
+}
+
+fn print_read_errors(errors: &[Error]) {
+    eprintln!("\nCould not read:");
+    for error in errors {
+        eprintln!("- {}", error);
+    }
+}
+
+fn print_redactions(redactions: &[FileRedactions], redacted: bool, paths: &PathFormatter) {
+    println!("\n{} secrets:", if redacted { "Redacted" } else { "Detected" });
+    for file in redactions {
+        let items: Vec<String> = file
+            .redactions
+            .iter()
+            .map(|r| format!("{} (line {})", r.kind, r.line))
+            .collect();
+        println!("- {}: {}", paths.format(&file.path), items.join(", "));
+    }
+}
+
+fn print_compressions(compressions: &[FileCompression], paths: &PathFormatter) {
+    println!("\nCompressed:");
+    for file in compressions {
+        println!(
+            "- {}: saved {} bytes (~{} tokens)",
+            paths.format(&file.path),
+            file.bytes_saved,
+            file.tokens_saved()
+        );
+    }
+}
+
+/// Loads config.txt and applies command-line overrides
+fn load_config(args: &Args) -> Config {
+    let config_path = get_config_path();
+    if !config_path.exists() {
+        eprintln!("Config file not found. Creating default config...");
+    }
+    let mut config = match Config::load(&config_path) {
+        Ok(config) => {
+            for (header, origin) in &config.unknown_sections {
+                eprintln!("Warning: Ignoring unknown config section '{}' ({})", header, origin);
+            }
+            eprintln!(
+                "Loaded {} excluded extensions and {} folder patterns from config",
+                config.excluded_extensions.len(),
+                config.folder_rules.len()
+            );
+            config
+        }
+        Err(e @ Error::Io { .. }) => {
+            eprintln!("Warning: Could not load config: {}. Using defaults.", e);
+            Config::with_defaults()
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(limit) = args.max_file_size {
+        config.max_file_size = Some((limit, RuleOrigin::flag("--max-file-size")));
+    }
+    if let Some(action) = args.oversize_action {
+        config.oversize_action = action;
+    }
+    if let Some(scope) = args.dedupe {
+        config.dedupe = scope;
+    }
+    if let Some(policy) = args.hidden {
+        config.hidden = policy;
+    }
+    if let Some(policy) = args.symlinks {
+        config.symlinks = policy;
+    }
+    if args.compress {
+        config.compress = true;
+    }
+    if let Some(ending) = args.line_endings {
+        config.line_endings = Some(ending);
+    }
+    if args.strip_bom {
+        config.strip_bom = true;
+    }
+    if args.final_newline {
+        config.final_newline = true;
+    }
+    // Content read on Windows must not differ from the same checkout elsewhere
+    if args.reproducible {
+        config.line_endings = Some(LineEnding::Lf);
+        config.strip_bom = true;
+    }
+    if args.git_tracked {
+        config.git_selection = Some(GitSelection::Tracked);
+    }
+    if args.git_info {
+        config.git_metadata = true;
+        config.git_diff = args.git_diff;
+    }
+    config.max_depth = args.max_depth;
+    config.min_depth = args.min_depth;
+    config.only = args.only.clone();
+    config.courses = match CourseFilter::new(&args.course, &args.exclude_course) {
+        Ok(filter) => filter,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    config.modified = match ModifiedRange::new(args.newer_than, args.older_than) {
+        Ok(range) => range,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(base) = &args.git_changed {
+        config.git_selection = Some(GitSelection::Changed {
+            base: base.clone(),
+            context: args.git_context,
+        });
+    }
+    config
+}
+
+fn run_explain(config: &Config, path: &Path, root: Option<&Path>, output: &str) {
+    let root = match root {
+        Some(r) => r.to_path_buf(),
+        None => env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+    };
+
+    match explain_path(path, &root, config, output) {
+        Ok(explanation) => println!("{}", explanation),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
 fn get_config_path() -> PathBuf {
     if let Ok(exe_path) = env::current_exe() {
         if let Some(exe_dir) = exe_path.parent() {
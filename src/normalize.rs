@@ -0,0 +1,129 @@
+use std::io::{self, Write};
+
+const BOM: char = '\u{FEFF}';
+
+/// Line ending used when normalizing content or writing the output file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_ascii_lowercase().as_str() {
+            "lf" => Ok(LineEnding::Lf),
+            "crlf" => Ok(LineEnding::Crlf),
+            _ => Err(format!("Invalid line ending '{}' (expected lf or crlf)", value)),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+}
+
+pub fn strip_bom(mut content: String) -> String {
+    if content.starts_with(BOM) {
+        content.drain(..BOM.len_utf8());
+    }
+    content
+}
+
+/// Converts CRLF, lone CR and LF line breaks to `ending`
+pub fn convert_line_endings(content: &str, ending: LineEnding) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' => {
+                chars.next_if_eq(&'\n');
+                out.push_str(ending.as_str());
+            }
+            '\n' => out.push_str(ending.as_str()),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Trims trailing line breaks and adds back exactly one, matching the file's
+/// own line ending. Empty content is left empty.
+pub fn ensure_final_newline(mut content: String) -> String {
+    let trimmed_len = content.trim_end_matches(['\r', '\n']).len();
+    if trimmed_len == 0 {
+        content.clear();
+        return content;
+    }
+    let ending = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    content.truncate(trimmed_len);
+    content.push_str(ending);
+    content
+}
+
+/// Writer that turns every `\n` not already preceded by `\r` into `\r\n`
+pub struct CrlfWriter<W: Write> {
+    inner: W,
+    last_was_cr: bool,
+}
+
+impl<W: Write> CrlfWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            last_was_cr: false,
+        }
+    }
+}
+
+impl<W: Write> Write for CrlfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut start = 0;
+        for (i, &b) in buf.iter().enumerate() {
+            let prev_cr = if i == 0 { self.last_was_cr } else { buf[i - 1] == b'\r' };
+            if b == b'\n' && !prev_cr {
+                self.inner.write_all(&buf[start..i])?;
+                self.inner.write_all(b"\r")?;
+                start = i;
+            }
+        }
+        self.inner.write_all(&buf[start..])?;
+        if let Some(&last) = buf.last() {
+            self.last_was_cr = last == b'\r';
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_content() {
+        let content = "\u{FEFF}a\r\nb\rc\n\n\n".to_string();
+        let lf = convert_line_endings(&strip_bom(content), LineEnding::Lf);
+        assert_eq!(lf, "a\nb\nc\n\n\n");
+        assert_eq!(ensure_final_newline(lf), "a\nb\nc\n");
+        assert_eq!(
+            ensure_final_newline(convert_line_endings("a\nb", LineEnding::Crlf)),
+            "a\r\nb\r\n"
+        );
+        assert_eq!(ensure_final_newline("\n\n".to_string()), "");
+    }
+
+    #[test]
+    fn test_crlf_writer() {
+        let mut out = CrlfWriter::new(Vec::new());
+        out.write_all(b"a\nb\r").unwrap();
+        out.write_all(b"\nc\r\n").unwrap();
+        assert_eq!(out.inner, b"a\r\nb\r\nc\r\n");
+    }
+}
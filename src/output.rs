@@ -1,90 +1,628 @@
+use std::env;
+use std::fmt::Write as _;
 use std::fs::File;
-use std::io::{BufWriter, Write};
-use std::path::Path;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, MAIN_SEPARATOR};
 
-use chrono::Local;
+use chrono::{DateTime, Local, Utc};
 
-use crate::scanner::ScanResult;
+use crate::config::{format_cutoff, ModifiedRange};
+use crate::error::Error;
+use crate::git::RepoInfo;
+use crate::normalize::{CrlfWriter, LineEnding};
+use crate::scanner::{ScanResult, SkippedEntry, Truncation};
 
-const SEPARATOR: &str = "================================================================";
-const FILE_SEPARATOR: &str = "================";
+pub const SEPARATOR: &str = "================================================================";
+pub const FILE_SEPARATOR: &str = "================";
+pub const COURSE_START: &str = "===[ COURSE: ";
+pub const COURSE_END: &str = "===[ END COURSE: ";
+pub const TRUNCATION_PREFIX: &str = "[... truncated ";
 
-pub fn write_output(output_path: &Path, result: &ScanResult) -> Result<(), String> {
-    let file = File::create(output_path)
-        .map_err(|e| format!("Failed to create output file: {}", e))?;
-    let mut writer = BufWriter::new(file);
+/// Marker line written between the head and tail of a truncated file
+pub fn truncation_marker(omitted_bytes: u64, omitted_lines: usize) -> String {
+    format!("{}{} bytes, {} lines ...]", TRUNCATION_PREFIX, omitted_bytes, omitted_lines)
+}
 
-    write_header(&mut writer)?;
-    write_file_list(&mut writer, &result.file_list)?;
-    write_courses(&mut writer, result)?;
+/// Number of line breaks a truncation marker stands for; `None` for other
+/// lines and for markers written before the count was recorded
+pub fn truncated_lines(line: &str) -> Option<usize> {
+    let rest = line.strip_prefix(TRUNCATION_PREFIX)?.strip_suffix(" lines ...]")?;
+    let (bytes, lines) = rest.split_once(" bytes, ")?;
+    bytes.parse::<u64>().ok()?;
+    lines.parse().ok()
+}
 
-    writer
-        .flush()
-        .map_err(|e| format!("Failed to flush output: {}", e))?;
+/// How file paths are shown in headers and reports
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum PathStyle {
+    /// Full path, e.g. `C:\Courses\Rust\main.rs`
+    #[default]
+    Absolute,
+    /// Path below the input directory, e.g. `Rust\main.rs`
+    Relative,
+    /// Relative path prefixed with the input directory's name, e.g. `Courses\Rust\main.rs`
+    RootPrefixed,
+}
 
-    Ok(())
+/// What the `Generated on:` header line shows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Timestamp {
+    /// Current local time
+    #[default]
+    Now,
+    /// Fixed UTC time in seconds since the Unix epoch (`SOURCE_DATE_EPOCH`)
+    Epoch(i64),
+    /// No timestamp line at all
+    Omit,
+}
+
+/// Presentation options that do not affect which files are scanned
+pub struct OutputOptions {
+    /// Prefix each content line with its line number
+    pub line_numbers: bool,
+    /// Add line count and byte size to each `File:` header block
+    pub file_stats: bool,
+    pub path_style: PathStyle,
+    /// Replace the user's home directory with `~` in absolute paths
+    pub redact_home: bool,
+    /// Use `/` as the path separator regardless of platform
+    pub forward_slashes: bool,
+    pub timestamp: Timestamp,
+    /// Show times in UTC rather than local time, so the output does not
+    /// depend on the machine's time zone
+    pub utc: bool,
+    /// Line ending of the output file itself
+    pub line_ending: LineEnding,
+    /// Append a section listing the paths the scan could not visit
+    pub skipped_entries: bool,
+    /// List each course and file with its line and byte offset after the
+    /// directory list
+    pub table_of_contents: bool,
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self {
+            line_numbers: false,
+            file_stats: false,
+            path_style: PathStyle::default(),
+            redact_home: false,
+            forward_slashes: false,
+            timestamp: Timestamp::default(),
+            utc: false,
+            line_ending: LineEnding::Lf,
+            skipped_entries: false,
+            table_of_contents: true,
+        }
+    }
+}
+
+/// Renders relative paths in the configured `PathStyle`
+pub struct PathFormatter {
+    style: PathStyle,
+    root: String,
+    root_name: String,
+    forward_slashes: bool,
+}
+
+impl PathFormatter {
+    /// `root` is the display form of the scanned directory
+    pub fn new(root: &str, options: &OutputOptions) -> Self {
+        let root_name = Path::new(root)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| root.to_string());
+        let root = if options.redact_home {
+            redact_home(root, home_dir().as_deref())
+        } else {
+            root.to_string()
+        };
+
+        Self {
+            style: options.path_style,
+            root,
+            root_name,
+            forward_slashes: options.forward_slashes,
+        }
+    }
+
+    /// Display form of the root directory
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    pub fn format(&self, relative: &str) -> String {
+        let path = match self.style {
+            PathStyle::Absolute => join(&self.root, relative),
+            PathStyle::Relative => relative.to_string(),
+            PathStyle::RootPrefixed => join(&self.root_name, relative),
+        };
+        if self.forward_slashes {
+            path.replace('\\', "/")
+        } else {
+            path
+        }
+    }
+}
+
+fn join(base: &str, relative: &str) -> String {
+    if base.ends_with(['/', '\\']) {
+        format!("{}{}", base, relative)
+    } else {
+        format!("{}{}{}", base, MAIN_SEPARATOR, relative)
+    }
+}
+
+fn home_dir() -> Option<String> {
+    env::var("HOME")
+        .or_else(|_| env::var("USERPROFILE"))
+        .ok()
+        .filter(|h| !h.is_empty())
+}
+
+/// Replaces a leading `home` directory in `path` with `~`
+fn redact_home(path: &str, home: Option<&str>) -> String {
+    let Some(home) = home.map(|h| h.trim_end_matches(['/', '\\'])) else {
+        return path.to_string();
+    };
+    match path.strip_prefix(home) {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => format!("~{}", rest),
+        _ => path.to_string(),
+    }
 }
 
-fn write_header(writer: &mut BufWriter<File>) -> Result<(), String> {
-    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S");
+pub fn write_output(output_path: &Path, result: &ScanResult, options: &OutputOptions) -> Result<(), Error> {
+    let file = File::create(output_path).map_err(|e| Error::io(output_path, e))?;
+    write_snapshot(BufWriter::new(file), result, options).map_err(|source| Error::Write {
+        path: Some(output_path.to_path_buf()),
+        source,
+    })
+}
+
+/// Writes the full snapshot (header, directory list and courses) to `writer`
+/// with the line ending from `options`
+pub fn render(writer: impl Write, result: &ScanResult, options: &OutputOptions) -> Result<(), Error> {
+    write_snapshot(writer, result, options).map_err(|source| Error::Write { path: None, source })
+}
+
+fn write_snapshot(writer: impl Write, result: &ScanResult, options: &OutputOptions) -> io::Result<()> {
+    match options.line_ending {
+        LineEnding::Lf => write_sections(writer, result, options),
+        LineEnding::Crlf => write_sections(CrlfWriter::new(writer), result, options),
+    }
+}
+
+fn write_sections(mut writer: impl Write, result: &ScanResult, options: &OutputOptions) -> io::Result<()> {
+    let paths = PathFormatter::new(&result.root, options);
+    if !options.table_of_contents {
+        write_front(&mut writer, result, options)?;
+        write_courses(&mut Tracker::new(&mut writer, options.line_ending), result, options, &paths)?;
+    } else {
+        // The contents list sits between the directory list and the courses,
+        // so both are written to memory first to learn where each entry starts
+        let mut front = Tracker::new(Vec::new(), options.line_ending);
+        write_front(&mut front, result, options)?;
+        let mut courses = Tracker::new(Vec::new(), options.line_ending);
+        let entries = write_courses(&mut courses, result, options, &paths)?;
+
+        writer.write_all(&front.inner)?;
+        writer.write_all(format_contents(front.position(), &entries, &paths, options.line_ending).as_bytes())?;
+        writer.write_all(&courses.inner)?;
+    }
+    if options.skipped_entries && !result.skipped.is_empty() {
+        write_skipped(&mut writer, &result.skipped, &paths)?;
+    }
+
+    writer.flush()
+}
+
+/// Header, repository section and directory list
+fn write_front(writer: &mut impl Write, result: &ScanResult, options: &OutputOptions) -> io::Result<()> {
+    write_header(writer, options, &result.modified)?;
+    if let Some(repo) = &result.git {
+        write_repository(writer, repo)?;
+    }
+    write_file_list(writer, &result.file_list)
+}
 
+fn format_timestamp(timestamp: Timestamp, utc: bool) -> Option<String> {
+    match timestamp {
+        Timestamp::Now if utc => Some(Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string()),
+        Timestamp::Now => Some(Local::now().format("%Y-%m-%d %H:%M:%S").to_string()),
+        Timestamp::Epoch(secs) => DateTime::from_timestamp(secs, 0)
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string()),
+        Timestamp::Omit => None,
+    }
+}
+
+fn write_header(writer: &mut impl Write, options: &OutputOptions, modified: &ModifiedRange) -> io::Result<()> {
     writeln!(
         writer,
         "This file is a merged representation of the directory, combining all text-based files into a single document."
-    )
-    .map_err(|e| format!("Write error: {}", e))?;
+    )?;
+
+    if let Some(timestamp) = format_timestamp(options.timestamp, options.utc) {
+        writeln!(writer, "Generated on: {}", timestamp)?;
+    }
+    let cutoff = |time: DateTime<Local>| {
+        if options.utc {
+            time.with_timezone(&Utc).format("%Y-%m-%d %H:%M:%S UTC").to_string()
+        } else {
+            format_cutoff(time)
+        }
+    };
+    if let Some(after) = modified.after {
+        writeln!(writer, "Files modified since: {}", cutoff(after))?;
+    }
+    if let Some(before) = modified.before {
+        writeln!(writer, "Files modified before: {}", cutoff(before))?;
+    }
+    writeln!(writer)
+}
+
+fn write_repository(writer: &mut impl Write, repo: &RepoInfo) -> io::Result<()> {
+    writeln!(writer, "{}", SEPARATOR)?;
+    writeln!(writer, "Repository")?;
+    writeln!(writer, "{}\n", SEPARATOR)?;
+
+    let status = match repo.changed_files {
+        0 => "clean".to_string(),
+        n => format!("dirty ({} changed files)", n),
+    };
+    writeln!(writer, "Branch: {}", repo.branch)?;
+    writeln!(writer, "HEAD: {} {}", repo.head, repo.subject)?;
+    writeln!(writer, "Status: {}\n", status)?;
+
+    if let Some(diffs) = repo.diff.as_deref().filter(|d| !d.is_empty()) {
+        writeln!(writer, "Uncommitted changes:")?;
+        for diff in diffs {
+            write!(writer, "{}", diff.text)?;
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
 
-    writeln!(writer, "Generated on: {}\n", timestamp)
-        .map_err(|e| format!("Write error: {}", e))?;
+fn write_file_list(writer: &mut impl Write, file_list: &str) -> io::Result<()> {
+    writeln!(writer, "{}", SEPARATOR)?;
+    writeln!(writer, "Directory List")?;
+    writeln!(writer, "{}\n", SEPARATOR)?;
+    writeln!(writer, "{}", file_list)?;
 
     Ok(())
 }
 
-fn write_file_list(writer: &mut BufWriter<File>, file_list: &str) -> Result<(), String> {
-    writeln!(writer, "{}", SEPARATOR).map_err(|e| format!("Write error: {}", e))?;
-    writeln!(writer, "Directory List").map_err(|e| format!("Write error: {}", e))?;
-    writeln!(writer, "{}\n", SEPARATOR).map_err(|e| format!("Write error: {}", e))?;
-    writeln!(writer, "{}", file_list).map_err(|e| format!("Write error: {}", e))?;
+fn write_skipped(writer: &mut impl Write, skipped: &[SkippedEntry], paths: &PathFormatter) -> io::Result<()> {
+    writeln!(writer, "{}", SEPARATOR)?;
+    writeln!(writer, "Skipped entries")?;
+    writeln!(writer, "{}\n", SEPARATOR)?;
+
+    for entry in skipped {
+        let suffix = if entry.is_dir { "/" } else { "" };
+        writeln!(writer, "- {}{}: {}", paths.format(&entry.relative_path), suffix, entry.reason)?;
+    }
 
     Ok(())
 }
 
+/// Line (counted from 0) and byte offset in the written output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Position {
+    line: usize,
+    byte: usize,
+}
+
+impl Position {
+    fn after(self, other: Position) -> Position {
+        Position {
+            line: self.line + other.line,
+            byte: self.byte + other.byte,
+        }
+    }
+}
+
+/// Passes writes through while counting lines and bytes as they will be in
+/// the output once line breaks are converted to `line_ending`
+struct Tracker<W: Write> {
+    inner: W,
+    line_ending: LineEnding,
+    position: Position,
+    last_was_cr: bool,
+}
+
+impl<W: Write> Tracker<W> {
+    fn new(inner: W, line_ending: LineEnding) -> Self {
+        Self {
+            inner,
+            line_ending,
+            position: Position::default(),
+            last_was_cr: false,
+        }
+    }
+
+    fn position(&self) -> Position {
+        self.position
+    }
+}
+
+impl<W: Write> Write for Tracker<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write_all(buf)?;
+        for (i, &b) in buf.iter().enumerate() {
+            if b == b'\n' {
+                self.position.line += 1;
+                let prev_cr = if i == 0 { self.last_was_cr } else { buf[i - 1] == b'\r' };
+                if self.line_ending == LineEnding::Crlf && !prev_cr {
+                    self.position.byte += 1;
+                }
+            }
+        }
+        self.position.byte += buf.len();
+        if let Some(&last) = buf.last() {
+            self.last_was_cr = last == b'\r';
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Where a course, or a file within the preceding course, starts relative to
+/// the start of the courses section
+enum ContentsEntry {
+    Course(String, Position),
+    File(String, Position),
+}
+
+/// Renders the table of contents that is written at `start`. Every entry
+/// shifts by the length of the table itself, which depends on the width of
+/// the numbers in it, so it is rendered until that length settles.
+fn format_contents(start: Position, entries: &[ContentsEntry], paths: &PathFormatter, line_ending: LineEnding) -> String {
+    let mut size = Position::default();
+    loop {
+        let base = start.after(size);
+        let mut text = format!("{}\nTable of Contents\n{}\n\n", SEPARATOR, SEPARATOR);
+        for entry in entries {
+            let location = |position: &Position| {
+                let at = base.after(*position);
+                format!("(line {}, byte {})", at.line + 1, at.byte)
+            };
+            match entry {
+                ContentsEntry::Course(name, position) => writeln!(text, "- {} {}", name, location(position)),
+                ContentsEntry::File(path, position) => {
+                    writeln!(text, "  - \"{}\" {}", paths.format(path), location(position))
+                }
+            }
+            .unwrap();
+        }
+        text.push('\n');
+
+        let rendered = Position {
+            line: text.matches('\n').count(),
+            byte: written_len(&text, line_ending),
+        };
+        if rendered == size {
+            return text;
+        }
+        size = rendered;
+    }
+}
+
 fn format_course_start(name: &str) -> String {
-    let prefix = format!("===[ COURSE: {} ]", name);
+    let prefix = format!("{}{} ]", COURSE_START, name);
     let padding = 64_usize.saturating_sub(prefix.len());
     format!("{}{}", prefix, "=".repeat(padding))
 }
 
 fn format_course_end(name: &str) -> String {
-    let prefix = format!("===[ END COURSE: {} ]", name);
+    let prefix = format!("{}{} ]", COURSE_END, name);
     let padding = 64_usize.saturating_sub(prefix.len());
     format!("{}{}", prefix, "=".repeat(padding))
 }
 
-fn write_courses(writer: &mut BufWriter<File>, result: &ScanResult) -> Result<(), String> {
-    writeln!(writer, "{}", SEPARATOR).map_err(|e| format!("Write error: {}", e))?;
-    writeln!(writer, "Courses").map_err(|e| format!("Write error: {}", e))?;
-    writeln!(writer, "{}\n", SEPARATOR).map_err(|e| format!("Write error: {}", e))?;
+/// Prefixes each line with its line number in the original file, keeping
+/// line breaks as they are. The truncation marker stays unnumbered and the
+/// tail continues at its real line number.
+fn format_numbered(content: &str, truncation: Option<Truncation>) -> String {
+    let lines = content.lines().count();
+    // The marker takes a line and its own line break is not in the file
+    let last = match truncation {
+        Some(truncation) => (lines + truncation.omitted_lines).saturating_sub(2),
+        None => lines,
+    };
+    let width = last.max(1).to_string().len();
+    let mut numbered = String::with_capacity(content.len() + lines * (width + 3));
+    let mut number = 1;
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        match truncation {
+            Some(truncation) if truncation.marker_line == i => {
+                numbered.push_str(line);
+                number = (number + truncation.omitted_lines).saturating_sub(1);
+            }
+            _ => {
+                numbered.push_str(&format!("{:>width$} | {}", number, line, width = width));
+                number += 1;
+            }
+        }
+    }
+    numbered
+}
+
+/// Whether `body` could be mistaken for the end of its block: it contains a
+/// file separator or course marker line, or is nothing but a duplicate
+/// reference. Such files get a `(N bytes)` length in their `File:` header and
+/// readers take exactly that many bytes instead of scanning for delimiters.
+pub fn collides_with_delimiters(body: &str) -> bool {
+    let only_line_is_reference = body.starts_with("[identical to \"") && body.lines().count() == 1;
+    only_line_is_reference
+        || body.lines().any(|line| {
+            let line = line.trim_end_matches('\r');
+            line == FILE_SEPARATOR || line.starts_with(COURSE_START) || line.starts_with(COURSE_END)
+        })
+}
+
+/// Length of `body` once written with `line_ending`
+fn written_len(body: &str, line_ending: LineEnding) -> usize {
+    match line_ending {
+        LineEnding::Lf => body.len(),
+        LineEnding::Crlf => {
+            let bare_newlines = body
+                .char_indices()
+                .filter(|&(i, c)| c == '\n' && !body[..i].ends_with('\r'))
+                .count();
+            body.len() + bare_newlines
+        }
+    }
+}
+
+/// Writes the courses section and returns where each course and file starts
+fn write_courses<W: Write>(
+    writer: &mut Tracker<W>,
+    result: &ScanResult,
+    options: &OutputOptions,
+    paths: &PathFormatter,
+) -> io::Result<Vec<ContentsEntry>> {
+    let mut entries = Vec::new();
+    writeln!(writer, "{}", SEPARATOR)?;
+    writeln!(writer, "Courses")?;
+    writeln!(writer, "{}\n", SEPARATOR)?;
 
     for course in &result.courses {
-        writeln!(writer, "{}", format_course_start(&course.name))
-            .map_err(|e| format!("Write error: {}", e))?;
+        entries.push(ContentsEntry::Course(course.name.clone(), writer.position()));
+        writeln!(writer, "{}", format_course_start(&course.name))?;
 
         for file in &course.files {
-            writeln!(writer, "{}", FILE_SEPARATOR)
-                .map_err(|e| format!("Write error: {}", e))?;
-            writeln!(writer, "File: \"{}\"", file.absolute_path)
-                .map_err(|e| format!("Write error: {}", e))?;
-            writeln!(writer, "{}", FILE_SEPARATOR)
-                .map_err(|e| format!("Write error: {}", e))?;
-            writeln!(writer, "{}", file.content)
-                .map_err(|e| format!("Write error: {}", e))?;
-            writeln!(writer).map_err(|e| format!("Write error: {}", e))?;
+            entries.push(ContentsEntry::File(file.relative_path.clone(), writer.position()));
+            let body = match &file.duplicate_of {
+                Some(original) => format!("[identical to \"{}\"]", paths.format(original)),
+                None if options.line_numbers => format_numbered(&file.content, file.truncation),
+                None => file.content.clone(),
+            };
+
+            writeln!(writer, "{}", FILE_SEPARATOR)?;
+            let path = paths.format(&file.relative_path);
+            if file.duplicate_of.is_none() && collides_with_delimiters(&body) {
+                writeln!(writer, "File: \"{}\" ({} bytes)", path, written_len(&body, options.line_ending))
+            } else {
+                writeln!(writer, "File: \"{}\"", path)
+            }?;
+            if let Some(repo) = &result.git {
+                match repo.last_commit(&file.relative_path) {
+                    Some(commit) => writeln!(writer, "Commit: {} {} {}", commit.hash, commit.date, commit.author),
+                    None => writeln!(writer, "Commit: (not committed)"),
+                }?;
+            }
+            if options.file_stats && file.duplicate_of.is_none() {
+                writeln!(writer, "Lines: {}, Bytes: {}", file.content.lines().count(), file.content.len())?;
+            }
+            writeln!(writer, "{}", FILE_SEPARATOR)?;
+            writeln!(writer, "{}", body)?;
+            writeln!(writer)?;
         }
 
-        writeln!(writer, "{}\n", format_course_end(&course.name))
-            .map_err(|e| format!("Write error: {}", e))?;
+        writeln!(writer, "{}\n", format_course_end(&course.name))?;
     }
 
-    Ok(())
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{CourseGroup, FileEntry, ScanStats};
+
+    #[test]
+    fn test_format_numbered() {
+        let content = (1..=10).map(|i| format!("line {}", i)).collect::<Vec<_>>().join("\n");
+        let numbered = format_numbered(&content, None);
+        let lines: Vec<&str> = numbered.lines().collect();
+
+        assert_eq!(lines.len(), 10);
+        assert_eq!(lines[0], " 1 | line 1");
+        assert_eq!(lines[9], "10 | line 10");
+        assert_eq!(format_numbered("", None), "");
+        assert_eq!(format_numbered("a\r\n\nb\n", None), "1 | a\r\n2 | \n3 | b\n");
+
+        // Lines 3 to 7 of the file are behind the marker
+        let excerpt = format!("line 1\nline 2\n{}\nline 8\n", truncation_marker(30, 6));
+        let truncation = Truncation { marker_line: 2, omitted_lines: 6 };
+        let numbered = format_numbered(&excerpt, Some(truncation));
+        let lines: Vec<&str> = numbered.lines().collect();
+        assert_eq!(lines[1], "2 | line 2");
+        assert_eq!(lines[2], "[... truncated 30 bytes, 6 lines ...]");
+        assert_eq!(lines[3], "8 | line 8");
+        assert!(numbered.ends_with('\n'));
+
+        // Only the recorded marker is left unnumbered
+        let lookalike = truncation_marker(1, 0);
+        assert_eq!(format_numbered(&lookalike, None), format!("1 | {}", lookalike));
+        assert_eq!(format_numbered(&lookalike, Some(Truncation { marker_line: 0, omitted_lines: 0 })), lookalike);
+    }
+
+    #[test]
+    fn test_table_of_contents_offsets() {
+        let file = |path: &str, content: &str| FileEntry {
+            relative_path: path.to_string(),
+            content: content.to_string(),
+            duplicate_of: None,
+            truncation: None,
+        };
+        let result = ScanResult {
+            root: "/courses".to_string(),
+            courses: vec![
+                CourseGroup {
+                    name: "Go".to_string(),
+                    files: vec![file("Go/main.go", "package main\n")],
+                },
+                CourseGroup {
+                    name: "Rust".to_string(),
+                    files: vec![file("Rust/a.rs", "fn a() {}\n\n"), file("Rust/b.rs", "fn b() {}")],
+                },
+            ],
+            file_list: "├───Go\n└───Rust".to_string(),
+            redactions: Vec::new(),
+            compressions: Vec::new(),
+            read_errors: Vec::new(),
+            skipped: Vec::new(),
+            modified: ModifiedRange::default(),
+            git: None,
+            stats: ScanStats::default(),
+        };
+
+        for line_ending in [LineEnding::Lf, LineEnding::Crlf] {
+            let options = OutputOptions {
+                path_style: PathStyle::Relative,
+                timestamp: Timestamp::Omit,
+                line_ending,
+                ..Default::default()
+            };
+            let mut out = Vec::new();
+            render(&mut out, &result, &options).unwrap();
+            let text = String::from_utf8(out).unwrap();
+            let lines: Vec<&str> = text.split_inclusive('\n').collect();
+
+            let toc: Vec<&str> = text.lines().filter(|l| l.ends_with(')') && l.contains("(line ")).collect();
+            assert_eq!(toc.len(), 5);
+            for entry in toc {
+                let (_, location) = entry.rsplit_once("(line ").unwrap();
+                let (line, byte) = location.trim_end_matches(')').split_once(", byte ").unwrap();
+                let (line, byte): (usize, usize) = (line.parse().unwrap(), byte.parse().unwrap());
+
+                let expected = if entry.starts_with("- ") { COURSE_START } else { FILE_SEPARATOR };
+                assert!(lines[line - 1].starts_with(expected), "{}", entry);
+                assert_eq!(lines[..line - 1].concat().len(), byte, "{}", entry);
+            }
+        }
+    }
+
+    #[test]
+    fn test_redact_home() {
+        assert_eq!(redact_home("/home/ann/courses", Some("/home/ann")), "~/courses");
+        assert_eq!(redact_home("/home/ann", Some("/home/ann/")), "~");
+        assert_eq!(redact_home("/home/anna/courses", Some("/home/ann")), "/home/anna/courses");
+        assert_eq!(redact_home("C:\\Users\\ann\\x", Some("C:\\Users\\ann")), "~\\x");
+    }
 }
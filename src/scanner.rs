@@ -1,18 +1,46 @@
-use indicatif::{ProgressBar, ProgressStyle};
+use chrono::{DateTime, Local};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 use rayon::prelude::*;
 use rayon::ThreadPoolBuilder;
-use std::collections::BTreeMap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
 use std::fs;
-use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
 use walkdir::WalkDir;
 
-use crate::config::Config;
+use crate::compress::{self, FileCompression};
+use crate::config::{
+    format_cutoff, Config, DedupeScope, HiddenPolicy, ModifiedRange, OversizeAction, RuleOrigin, SymlinkPolicy,
+};
+use crate::error::Error;
+use crate::git::{GitFiles, RepoInfo};
+use crate::normalize;
+use crate::output::truncation_marker;
+use crate::secrets::{FileRedactions, Redaction, SecretDetector};
+use crate::DEFAULT_OUTPUT_FILENAME;
 
+#[derive(Clone)]
 pub struct FileEntry {
-    pub absolute_path: String,
     pub relative_path: String,
     pub content: String,
+    /// Relative path of an earlier file with identical content; `content` is
+    /// emptied when set
+    pub duplicate_of: Option<String>,
+    /// Where the middle of an oversized file was left out; set only for
+    /// files read as a head and tail excerpt
+    pub truncation: Option<Truncation>,
+}
+
+/// Position of the marker line standing in for the omitted middle of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Truncation {
+    /// Index of the marker line in the content, counting from 0
+    pub marker_line: usize,
+    /// Line breaks in the omitted part of the file
+    pub omitted_lines: usize,
 }
 
 pub struct CourseGroup {
@@ -21,34 +49,369 @@ pub struct CourseGroup {
 }
 
 pub struct ScanResult {
+    /// Display form of the scanned root directory
+    pub root: String,
     pub courses: Vec<CourseGroup>,
     pub file_list: String,
+    /// Files with detected secrets, by relative path; the secrets are
+    /// redacted in the content unless `redact_secrets` is off
+    pub redactions: Vec<FileRedactions>,
+    /// Files that compression shrank, by relative path
+    pub compressions: Vec<FileCompression>,
+    /// Files that were selected but could not be read, ordered by path
+    pub read_errors: Vec<Error>,
+    /// Paths the walk could not visit, in walk order
+    pub skipped: Vec<SkippedEntry>,
+    /// Modification time cutoffs the files were selected with
+    pub modified: ModifiedRange,
+    /// Repository state when `git_metadata` is enabled
+    pub git: Option<RepoInfo>,
     pub stats: ScanStats,
 }
 
+impl ScanResult {
+    /// A result holding just `files`, grouped into courses and listed in the
+    /// directory tree, with an empty root and no statistics
+    pub fn from_files(files: Vec<FileEntry>) -> Self {
+        let file_list = build_file_list(files.iter().map(|f| f.relative_path.as_str()));
+        ScanResult {
+            root: String::new(),
+            courses: group_into_courses(files),
+            file_list,
+            redactions: Vec::new(),
+            compressions: Vec::new(),
+            read_errors: Vec::new(),
+            skipped: Vec::new(),
+            modified: ModifiedRange::default(),
+            git: None,
+            stats: ScanStats::default(),
+        }
+    }
+
+    /// Whether some entry could not be visited or read, which `--strict`
+    /// refuses to write output for
+    pub fn is_incomplete(&self) -> bool {
+        !self.skipped.is_empty() || !self.read_errors.is_empty()
+    }
+}
+
+/// What a scan would do with every path below its root, found without
+/// reading more than the head of each file
+pub struct ScanPlan {
+    /// Display form of the scanned root directory
+    pub root: String,
+    /// Walked paths in walk order, with binary files already told apart
+    pub entries: Vec<WalkEntry>,
+}
+
 #[derive(Default)]
 pub struct ScanStats {
     pub total_files: usize,
     pub files_processed: usize,
     pub files_excluded: usize,
     pub folders_skipped: usize,
+    pub files_binary: usize,
+    pub files_too_large: usize,
+    pub files_truncated: usize,
+    pub files_unreadable: usize,
+    /// Directories and files the walk could not visit
+    pub entries_skipped: usize,
+    /// Files and folders left out for a hidden name
+    pub hidden_skipped: usize,
+    /// Files left out as OS junk
+    pub junk_skipped: usize,
+    /// Files modified before the `--newer-than` cutoff
+    pub files_too_old: usize,
+    /// Files modified after the `--older-than` cutoff
+    pub files_too_new: usize,
+    /// Symbolic links that were skipped or listed rather than followed
+    pub symlinks: usize,
+    /// Files reached a second time through a followed link
+    pub files_repeated: usize,
+    /// Files left out by the git selection or the depth limits, and folders
+    /// below `--max-depth`
+    pub files_not_selected: usize,
+    /// Courses left out by `--course` or `--exclude-course`
+    pub courses_skipped: usize,
+    pub files_deduplicated: usize,
+    pub bytes_saved: u64,
+    pub compressed_bytes_saved: u64,
+    pub secrets_redacted: usize,
+    pub files_with_secrets: usize,
+    /// Files read from disk rather than taken from a `ScanCache`
+    pub files_read: usize,
     pub worker_count: usize,
     pub courses_found: usize,
 }
 
+/// Why a walked path was or was not selected for the output
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    Included,
+    ExcludedExtension(String),
+    ExcludedFolder(String),
+    Binary,
+    /// Larger than the size limit and skipped
+    TooLarge(u64),
+    /// Larger than the size limit and cut down to head/tail excerpts
+    Truncated(u64),
+    /// Left out by a selection option such as `--git-changed` or `--only`,
+    /// with the reason
+    NotSelected(String),
+    /// In a course left out by `--course` or `--exclude-course`, with the reason
+    OtherCourse(String),
+    /// Could not be visited, e.g. an unreadable directory or a broken symlink
+    Unreadable(String),
+    /// Name starts with a dot and `hidden` is set to exclude
+    Hidden,
+    /// On the built-in OS junk list, with the matching pattern
+    Junk(String),
+    /// Modified before the `--newer-than` cutoff, shown with the cutoff
+    TooOld(String),
+    /// Modified at or after the `--older-than` cutoff, shown with the cutoff
+    TooNew(String),
+    /// A symbolic link that is not followed, with its target
+    Symlink(String),
+    /// Reached through a link to a file that is already included, with the
+    /// relative path it is included under
+    SameFile(String),
+}
+
+impl Decision {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Decision::Included => "included",
+            Decision::ExcludedExtension(_) | Decision::ExcludedFolder(_) => "excluded",
+            Decision::Binary => "binary",
+            Decision::TooLarge(_) => "too large",
+            Decision::Truncated(_) => "truncated",
+            Decision::NotSelected(_) => "not selected",
+            Decision::OtherCourse(_) => "other course",
+            Decision::Unreadable(_) => "skipped",
+            Decision::Hidden => "hidden",
+            Decision::Junk(_) => "junk",
+            Decision::TooOld(_) => "too old",
+            Decision::TooNew(_) => "too new",
+            Decision::Symlink(_) => "symlink",
+            Decision::SameFile(_) => "repeated",
+        }
+    }
+
+    pub fn reason(&self) -> Option<String> {
+        match self {
+            Decision::Included => None,
+            Decision::ExcludedExtension(ext) => Some(format!("extension {}", ext)),
+            Decision::ExcludedFolder(pattern) => Some(format!("folder pattern \"{}\"", pattern)),
+            Decision::Binary => Some("binary content".to_string()),
+            Decision::TooLarge(limit) => Some(format!("size limit of {} bytes", limit)),
+            Decision::Truncated(limit) => Some(format!("truncated to {} bytes", limit)),
+            Decision::NotSelected(reason) | Decision::Unreadable(reason) => Some(reason.clone()),
+            Decision::OtherCourse(reason) => Some(format!("course {}", reason)),
+            Decision::Hidden => Some("hidden name".to_string()),
+            Decision::Junk(pattern) => Some(format!("OS junk file \"{}\"", pattern)),
+            Decision::TooOld(cutoff) => Some(format!("modified before {}", cutoff)),
+            Decision::TooNew(cutoff) => Some(format!("modified after {}", cutoff)),
+            Decision::Symlink(target) => Some(format!("-> {}", target)),
+            Decision::SameFile(original) => Some(format!("same file as {}", original)),
+        }
+    }
+}
+
+/// A path left out of the scan because it could not be visited
+pub struct SkippedEntry {
+    pub relative_path: String,
+    pub is_dir: bool,
+    pub reason: String,
+}
+
+/// A path the walk came across and what the scan does with it
+pub struct WalkEntry {
+    pub path: PathBuf,
+    pub relative_path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub decision: Decision,
+}
+
+/// Bytes inspected when deciding whether a file is binary
+const BINARY_SNIFF_LEN: usize = 8000;
+
+/// Builder for a directory scan:
+/// `Scanner::new(root).config(config).scan()`
+pub struct Scanner {
+    root: PathBuf,
+    config: Config,
+    output_filename: String,
+    verbose: bool,
+    show_progress: bool,
+    detect_secrets: bool,
+}
+
+impl Scanner {
+    /// Scan of `root` with the built-in default config, skipping
+    /// `DEFAULT_OUTPUT_FILENAME`, without progress output
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            config: Config::with_defaults(),
+            output_filename: DEFAULT_OUTPUT_FILENAME.to_string(),
+            verbose: false,
+            show_progress: false,
+            detect_secrets: false,
+        }
+    }
+
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// File in the root that is never scanned, normally the output file
+    pub fn output_filename(mut self, filename: impl Into<String>) -> Self {
+        self.output_filename = filename.into();
+        self
+    }
+
+    /// Print every processed and skipped file
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Draw the spinner and progress bar on stderr
+    pub fn show_progress(mut self, show_progress: bool) -> Self {
+        self.show_progress = show_progress;
+        self
+    }
+
+    /// Look for secrets even when `redact_secrets` is off; they are reported
+    /// in `ScanResult::redactions` but left in the content
+    pub fn detect_secrets(mut self, detect_secrets: bool) -> Self {
+        self.detect_secrets = detect_secrets;
+        self
+    }
+
+    pub fn scan(&self) -> Result<ScanResult, Error> {
+        self.scan_cached(&mut ScanCache::default())
+    }
+
+    /// Walks the root exactly like `scan` and records the decision for every
+    /// path, reading only the head of selected files to tell binary ones apart
+    pub fn plan(&self) -> Result<ScanPlan, Error> {
+        let root = self.root.canonicalize().map_err(|e| Error::io(&self.root, e))?;
+        let mut entries = walk_entries(&root, &self.config, &root.join(&self.output_filename))?;
+        for entry in &mut entries {
+            let readable = matches!(entry.decision, Decision::Included | Decision::Truncated(_));
+            if readable && sniff_binary(&entry.path).unwrap_or(false) {
+                entry.decision = Decision::Binary;
+            }
+        }
+        Ok(ScanPlan {
+            root: display_path(&root),
+            entries,
+        })
+    }
+
+    /// Like `scan`, but takes unchanged files from `cache` instead of reading
+    /// them again, and leaves this scan's files in it for the next one
+    pub fn scan_cached(&self, cache: &mut ScanCache) -> Result<ScanResult, Error> {
+        scan_directory(
+            &self.root,
+            &self.config,
+            &self.output_filename,
+            self.verbose,
+            self.show_progress,
+            self.detect_secrets,
+            cache,
+        )
+    }
+
+    /// Whether changes to the `changed` paths affect the output of the last
+    /// scan that filled `cache`: walking again changes what the output lists,
+    /// or one of them is a file the output reads. Cached content
+    /// of the changed paths is dropped either way.
+    pub fn is_affected_by(&self, cache: &mut ScanCache, changed: &[PathBuf]) -> Result<bool, Error> {
+        let root = self.root.canonicalize().map_err(|e| Error::io(&self.root, e))?;
+        let entries = walk_entries(&root, &self.config, &root.join(&self.output_filename))?;
+
+        cache.files.retain(|path, _| !changed.iter().any(|c| path.starts_with(c)));
+        let reads_changed = entries
+            .iter()
+            .any(|e| reads_content(&e.decision) && changed.contains(&e.path));
+        Ok(reads_changed || cache.decisions != decisions_of(&entries))
+    }
+}
+
+/// Files read by earlier scans of the same root, so watch mode only reads
+/// what changed
+#[derive(Default)]
+pub struct ScanCache {
+    /// Relative path and decision of every walked path the last scan's
+    /// output shows
+    decisions: Vec<(String, Decision)>,
+    files: HashMap<PathBuf, CachedFile>,
+}
+
+/// A file as the last scan processed it, reused while its size, modification
+/// time and decision stay the same and no change to it was seen
+struct CachedFile {
+    size: u64,
+    /// `None` when the platform has no modification times; such files are
+    /// always read again
+    modified: Option<SystemTime>,
+    decision: Decision,
+    content: FileContent,
+}
+
+/// What reading and processing one file produced
+#[derive(Clone)]
+enum FileContent {
+    Binary,
+    Text {
+        file: FileEntry,
+        redactions: Vec<Redaction>,
+        compressed_saved: u64,
+    },
+}
+
+/// Walked paths that appear in the output as files, links or skipped
+/// entries; other paths can come and go without changing it
+fn decisions_of(entries: &[WalkEntry]) -> Vec<(String, Decision)> {
+    entries
+        .iter()
+        .filter(|e| reads_content(&e.decision) || matches!(e.decision, Decision::Symlink(_) | Decision::Unreadable(_)))
+        .map(|e| (e.relative_path.clone(), e.decision.clone()))
+        .collect()
+}
+
+/// Whether the output contains the file's content, so a change to it matters
+fn reads_content(decision: &Decision) -> bool {
+    matches!(decision, Decision::Included | Decision::Binary | Decision::Truncated(_))
+}
+
+/// Scans `root` and reads every included file. `show_progress` draws the
+/// spinner and progress bar on stderr. Files that cannot be read are
+/// collected in `ScanResult::read_errors` instead of failing the scan.
+/// Secrets are looked for when `redact_secrets` or `detect_secrets` is set,
+/// and only replaced with the former. Files in `cache` are reused as long as
+/// their size and decision match, and `cache` is refilled from this scan.
 pub fn scan_directory(
     root: &Path,
     config: &Config,
     output_filename: &str,
     verbose: bool,
-) -> Result<ScanResult, String> {
-    let root = root
-        .canonicalize()
-        .map_err(|e| format!("Failed to resolve path: {}", e))?;
+    show_progress: bool,
+    detect_secrets: bool,
+    cache: &mut ScanCache,
+) -> Result<ScanResult, Error> {
+    let root = root.canonicalize().map_err(|e| Error::io(root, e))?;
 
     let output_path = root.join(output_filename);
 
     let scan_spinner = ProgressBar::new_spinner();
+    if !show_progress {
+        scan_spinner.set_draw_target(ProgressDrawTarget::hidden());
+    }
     scan_spinner.set_style(
         ProgressStyle::with_template("{spinner:.cyan} {msg}")
             .unwrap()
@@ -58,52 +421,73 @@ pub fn scan_directory(
     scan_spinner.set_message("Scanning directory...");
 
     let mut stats = ScanStats::default();
-    let mut file_paths: Vec<(PathBuf, String)> = Vec::new();
-
-    let mut walker = WalkDir::new(&root).into_iter();
-    loop {
-        let entry = match walker.next() {
-            Some(Ok(e)) => e,
-            Some(Err(_)) => continue,
-            None => break,
-        };
-
-        let path = entry.path().to_path_buf();
+    let mut file_paths: Vec<WalkEntry> = Vec::new();
+    let mut skipped = Vec::new();
+    let mut links: Vec<(String, String)> = Vec::new();
 
-        // Prune excluded directories (but never the root itself)
-        if entry.file_type().is_dir() {
-            if path != root {
-                let name = entry.file_name().to_string_lossy();
-                if config.should_exclude_dir(&name) {
-                    walker.skip_current_dir();
-                    stats.folders_skipped += 1;
+    let entries = walk_entries(&root, config, &output_path)?;
+    cache.decisions = decisions_of(&entries);
+    for entry in entries {
+        match entry.decision {
+            Decision::Symlink(target) => {
+                stats.symlinks += 1;
+                if config.symlinks == SymlinkPolicy::List {
+                    links.push((entry.relative_path, target));
                 }
             }
-            continue;
-        }
-
-        // Skip the output file
-        if path == output_path {
-            continue;
-        }
-
-        stats.total_files += 1;
-
-        let extension = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .map(|e| format!(".{}", e))
-            .unwrap_or_default();
-
-        if config.should_exclude(&extension) {
-            stats.files_excluded += 1;
-        } else {
-            // Store relative path from root
-            let relative = path
-                .strip_prefix(&root)
-                .map(|p| p.to_string_lossy().to_string())
-                .unwrap_or_else(|_| path.file_name().unwrap_or_default().to_string_lossy().to_string());
-            file_paths.push((path, relative));
+            Decision::Hidden if entry.is_dir => stats.hidden_skipped += 1,
+            Decision::Hidden => {
+                stats.total_files += 1;
+                stats.hidden_skipped += 1;
+            }
+            Decision::Junk(_) => {
+                stats.total_files += 1;
+                stats.junk_skipped += 1;
+            }
+            Decision::TooOld(_) => {
+                stats.total_files += 1;
+                stats.files_too_old += 1;
+            }
+            Decision::TooNew(_) => {
+                stats.total_files += 1;
+                stats.files_too_new += 1;
+            }
+            Decision::SameFile(_) => {
+                stats.total_files += 1;
+                stats.files_repeated += 1;
+            }
+            Decision::Unreadable(reason) => {
+                stats.entries_skipped += 1;
+                skipped.push(SkippedEntry {
+                    relative_path: entry.relative_path,
+                    is_dir: entry.is_dir,
+                    reason,
+                });
+            }
+            Decision::NotSelected(_) if entry.is_dir => stats.files_not_selected += 1,
+            Decision::NotSelected(_) => {
+                stats.total_files += 1;
+                stats.files_not_selected += 1;
+            }
+            Decision::OtherCourse(_) => stats.courses_skipped += 1,
+            Decision::ExcludedFolder(_) => stats.folders_skipped += 1,
+            Decision::ExcludedExtension(_) => {
+                stats.total_files += 1;
+                stats.files_excluded += 1;
+            }
+            Decision::TooLarge(_) => {
+                stats.total_files += 1;
+                stats.files_too_large += 1;
+            }
+            Decision::Truncated(_) => {
+                stats.total_files += 1;
+                stats.files_truncated += 1;
+                file_paths.push(entry);
+            }
+            Decision::Included | Decision::Binary => {
+                stats.total_files += 1;
+                file_paths.push(entry);
+            }
         }
     }
 
@@ -113,7 +497,10 @@ pub fn scan_directory(
     ));
 
     // Build simple file list
-    let file_list = build_file_list(&file_paths);
+    let file_list = build_tree(
+        file_paths.iter().map(|e| e.relative_path.as_str()),
+        links.iter().map(|(path, target)| (path.as_str(), target.as_str())),
+    );
 
     // Calculate worker count: ceil(file_count / 10), minimum 1, maximum 50
     let files_to_process = file_paths.len();
@@ -128,9 +515,12 @@ pub fn scan_directory(
     let pool = ThreadPoolBuilder::new()
         .num_threads(worker_count)
         .build()
-        .map_err(|e| format!("Failed to create thread pool: {}", e))?;
+        .map_err(Error::ThreadPool)?;
 
     let progress = ProgressBar::new(files_to_process as u64);
+    if !show_progress {
+        progress.set_draw_target(ProgressDrawTarget::hidden());
+    }
     progress.set_style(
         ProgressStyle::with_template(
             "Reading files {bar:40.cyan/blue} {pos}/{len} ({percent}%) {msg}",
@@ -140,59 +530,676 @@ pub fn scan_directory(
     );
     progress.set_message(format!("{} workers", worker_count));
 
+    let detector = (config.redact_secrets || detect_secrets).then(|| SecretDetector::new(config));
+
     // Process files in parallel using custom pool
-    let files: Vec<FileEntry> = pool.install(|| {
+    // Watchers can miss events, so an edit that keeps the size is caught by
+    // the modification time
+    let reads: Vec<(Result<FileContent, Error>, Option<SystemTime>, bool)> = pool.install(|| {
         file_paths
             .par_iter()
-            .filter_map(|(path, relative)| {
-                let result = match fs::read_to_string(path) {
-                    Ok(content) => {
-                        if verbose {
-                            progress.println(format!("Processing: {}", relative));
-                        }
-                        let abs_path = path.to_string_lossy().to_string();
-                        // Strip Windows extended-length path prefix
-                        let abs_path = abs_path.strip_prefix(r"\\?\").unwrap_or(&abs_path).to_string();
-                        Some(FileEntry {
-                            absolute_path: abs_path,
-                            relative_path: relative.clone(),
-                            content,
-                        })
-                    }
-                    Err(e) => {
-                        progress.println(format!("Warning: Could not read '{}': {}", relative, e));
-                        None
-                    }
+            .map(|entry| {
+                let modified = fs::metadata(&entry.path).and_then(|m| m.modified()).ok();
+                let cached = cache.files.get(&entry.path).filter(|c| {
+                    c.size == entry.size && c.modified.is_some() && c.modified == modified && c.decision == entry.decision
+                });
+                let read = match cached {
+                    Some(cached) => (Ok(cached.content.clone()), modified, true),
+                    None => (read_file(entry, config, detector.as_ref(), verbose, &progress), modified, false),
                 };
                 progress.inc(1);
-                result
+                read
             })
             .collect()
     });
 
     progress.finish_with_message("done");
 
-    let mut files = files;
-    files.sort_by(|a, b| a.absolute_path.cmp(&b.absolute_path));
+    let mut files = Vec::new();
+    let mut redactions = Vec::new();
+    let mut compressions = Vec::new();
+    let mut read_errors = Vec::new();
+    cache.files.clear();
+    for (entry, (read, modified, was_cached)) in file_paths.into_iter().zip(reads) {
+        if !was_cached {
+            stats.files_read += 1;
+        }
+        let content = match read {
+            Ok(content) => content,
+            Err(e) => {
+                read_errors.push((entry.relative_path, e));
+                continue;
+            }
+        };
+        match &content {
+            FileContent::Binary => stats.files_binary += 1,
+            FileContent::Text { file, redactions: found, compressed_saved } => {
+                if *compressed_saved > 0 {
+                    stats.compressed_bytes_saved += compressed_saved;
+                    compressions.push(FileCompression {
+                        path: file.relative_path.clone(),
+                        bytes_saved: *compressed_saved,
+                    });
+                }
+                if !found.is_empty() {
+                    stats.secrets_redacted += found.len();
+                    redactions.push(FileRedactions {
+                        path: file.relative_path.clone(),
+                        redactions: found.clone(),
+                    });
+                }
+                files.push(file.clone());
+            }
+        }
+        cache.files.insert(
+            entry.path,
+            CachedFile {
+                size: entry.size,
+                modified,
+                decision: entry.decision,
+                content,
+            },
+        );
+    }
 
-    let courses = group_into_courses(files);
+    // Compare path components so the order matches the tree and does not
+    // depend on the platform's separator
+    files.sort_by(|a, b| path_components(&a.relative_path).cmp(path_components(&b.relative_path)));
+    redactions.sort_by(|a, b| path_components(&a.path).cmp(path_components(&b.path)));
+    compressions.sort_by(|a, b| path_components(&a.path).cmp(path_components(&b.path)));
+    read_errors.sort_by(|(a, _), (b, _)| path_components(a).cmp(path_components(b)));
+    let read_errors: Vec<Error> = read_errors.into_iter().map(|(_, error)| error).collect();
+    stats.files_with_secrets = redactions.len();
+    stats.files_unreadable = read_errors.len();
+
+    let mut courses = group_into_courses(files);
+    dedupe_files(&mut courses, config.dedupe, &mut stats);
     stats.courses_found = courses.len();
     stats.files_processed = courses.iter().map(|c| c.files.len()).sum();
 
+    let git = if config.git_metadata {
+        // Only changes to files in the output are shown, so excluded files
+        // cannot leak in through the diff
+        let included: Vec<String> = courses
+            .iter()
+            .flat_map(|c| c.files.iter().map(|f| f.relative_path.clone()))
+            .collect();
+        let mut info = RepoInfo::load(&root, &included, config.git_diff)?;
+        // The diff holds raw file content, so it gets the same redaction
+        if let (Some(detector), Some(diffs)) = (detector.as_ref().filter(|_| config.redact_secrets), &mut info.diff) {
+            for diff in diffs {
+                diff.text = detector.redact_diff(Path::new(&diff.path), &diff.text).0;
+            }
+        }
+        Some(info)
+    } else {
+        None
+    };
+
     Ok(ScanResult {
+        root: display_path(&root),
         courses,
         file_list,
+        redactions,
+        compressions,
+        read_errors,
+        skipped,
+        modified: config.modified,
+        git,
         stats,
     })
 }
 
-fn group_into_courses(files: Vec<FileEntry>) -> Vec<CourseGroup> {
+/// Walks `root` and classifies every path against the config without reading
+/// file contents. Excluded folders are reported once and not descended into.
+pub fn walk_entries(root: &Path, config: &Config, output_path: &Path) -> Result<Vec<WalkEntry>, Error> {
+    let git_files = match &config.git_selection {
+        Some(selection) => Some(GitFiles::load(root, selection)?),
+        None => None,
+    };
+    let follow = config.symlinks == SymlinkPolicy::Follow;
+    let mut entries: Vec<WalkEntry> = Vec::new();
+
+    for (start, start_depth) in walk_starts(root, &config.only)? {
+        if let Some(course) = start.strip_prefix(root).ok().and_then(|p| p.iter().next()) {
+            if config.courses.rejection(&course.to_string_lossy()).is_some() {
+                continue;
+            }
+        }
+        // The walk never prunes where it starts, so the folders leading to an
+        // --only path get the checks they would have had on the way down
+        if let Some(entry) = excluded_ancestor(root, &start, config) {
+            // Several --only paths can sit below the same folder
+            if !entries.iter().any(|e| e.path == entry.path) {
+                entries.push(entry);
+            }
+            continue;
+        }
+        // One level past the limit is walked so entries there are reported
+        let mut walker = WalkDir::new(&start).follow_links(follow);
+        if let Some(max_depth) = config.max_depth {
+            walker = walker.max_depth((max_depth + 1).saturating_sub(start_depth));
+        }
+        walk_from(walker, root, start_depth, config, output_path, git_files.as_ref(), &mut entries);
+    }
+
+    if follow {
+        mark_repeated_files(&mut entries);
+    }
+
+    Ok(entries)
+}
+
+/// Where to start walking and how many levels below `root` that is: the root
+/// itself, or each `--only` subpath that is not inside another one
+fn walk_starts(root: &Path, only: &[PathBuf]) -> Result<Vec<(PathBuf, usize)>, Error> {
+    if only.is_empty() {
+        return Ok(vec![(root.to_path_buf(), 0)]);
+    }
+
+    let mut subpaths = Vec::new();
+    for subpath in only {
+        if !subpath.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(Error::Config {
+                origin: RuleOrigin::flag("--only"),
+                message: format!("{:?} is not a relative path inside the root", subpath),
+            });
+        }
+        subpaths.push(subpath);
+    }
+    subpaths.sort();
+    subpaths.dedup();
+
+    let mut starts: Vec<(PathBuf, usize)> = Vec::new();
+    for subpath in subpaths {
+        if !starts.iter().any(|(start, _)| root.join(subpath).starts_with(start)) {
+            starts.push((root.join(subpath), subpath.components().count()));
+        }
+    }
+    Ok(starts)
+}
+
+/// The first path from `root` down to `start` that a walk from the root would
+/// have reported instead of entering: a link not followed or leading outside
+/// the root, an excluded folder or a hidden one. A file at `start` is left to
+/// `walk_from`.
+fn excluded_ancestor(root: &Path, start: &Path, config: &Config) -> Option<WalkEntry> {
+    let follow = config.symlinks == SymlinkPolicy::Follow;
+    let mut path = root.to_path_buf();
+    for component in start.strip_prefix(root).ok()?.components() {
+        path.push(component);
+        let is_link = fs::symlink_metadata(&path).is_ok_and(|m| m.is_symlink());
+        let is_dir = fs::metadata(&path).is_ok_and(|m| m.is_dir());
+        let decision = if is_link && fs::metadata(&path).is_err() {
+            Some(Decision::Unreadable("broken symlink".to_string()))
+        } else if is_link && !follow {
+            Some(Decision::Symlink(link_target(&path)))
+        } else if is_link && !fs::canonicalize(&path).is_ok_and(|real| real.starts_with(root)) {
+            Some(Decision::Unreadable("link points outside the root".to_string()))
+        } else if !is_dir {
+            return None;
+        } else {
+            let name = component.as_os_str().to_string_lossy();
+            match config.matching_folder_pattern(&name) {
+                Some(pattern) => Some(Decision::ExcludedFolder(pattern.to_string())),
+                None => hidden_decision(config, &name, true),
+            }
+        };
+        if let Some(decision) = decision {
+            return Some(WalkEntry {
+                relative_path: relative_to(root, &path),
+                path,
+                is_dir,
+                size: 0,
+                decision,
+            });
+        }
+    }
+    None
+}
+
+/// Classifies everything `walker` yields; entry depths are offset by
+/// `start_depth` so limits always count from `root`
+fn walk_from(
+    walker: WalkDir,
+    root: &Path,
+    start_depth: usize,
+    config: &Config,
+    output_path: &Path,
+    git_files: Option<&GitFiles>,
+    entries: &mut Vec<WalkEntry>,
+) {
+    let follow = config.symlinks == SymlinkPolicy::Follow;
+    let min_depth = config.min_depth.unwrap_or(0);
+
+    let mut walker = walker.into_iter();
+    loop {
+        let entry = match walker.next() {
+            Some(Ok(e)) => e,
+            Some(Err(e)) => {
+                let path = e.path().unwrap_or(root).to_path_buf();
+                // A link back to one of its own ancestors is listed, not walked again
+                let decision = if e.loop_ancestor().is_some() {
+                    Decision::Symlink(link_target(&path))
+                } else if fs::symlink_metadata(&path).is_ok_and(|m| m.is_symlink()) && fs::metadata(&path).is_err() {
+                    Decision::Unreadable("broken symlink".to_string())
+                } else {
+                    Decision::Unreadable(match e.io_error() {
+                        Some(io) => io.to_string(),
+                        None => e.to_string(),
+                    })
+                };
+                entries.push(WalkEntry {
+                    relative_path: relative_to(root, &path),
+                    is_dir: fs::metadata(&path).is_ok_and(|m| m.is_dir()),
+                    path,
+                    size: 0,
+                    decision,
+                });
+                continue;
+            }
+            None => break,
+        };
+
+        let path = entry.path().to_path_buf();
+        let depth = start_depth + entry.depth();
+
+        // Entries past the depth limit are reported once, not descended into
+        if let Some(max_depth) = config.max_depth.filter(|&max| depth > max && path != output_path) {
+            if entry.file_type().is_dir() {
+                walker.skip_current_dir();
+            }
+            entries.push(WalkEntry {
+                relative_path: relative_to(root, &path),
+                is_dir: entry.file_type().is_dir(),
+                path,
+                size: 0,
+                decision: Decision::NotSelected(format!("deeper than --max-depth {}", max_depth)),
+            });
+            continue;
+        }
+
+        // Shallow files are left out, but their folders are still walked
+        if depth < min_depth && !entry.file_type().is_dir() && path != output_path {
+            entries.push(WalkEntry {
+                relative_path: relative_to(root, &path),
+                is_dir: false,
+                size: entry.metadata().map(|m| m.len()).unwrap_or(0),
+                path,
+                decision: Decision::NotSelected(format!("shallower than --min-depth {}", min_depth)),
+            });
+            continue;
+        }
+
+        // Courses are picked by name before anything inside them is read
+        if depth == 1 && path != output_path {
+            if let Some(reason) = config.courses.rejection(&entry.file_name().to_string_lossy()) {
+                if entry.file_type().is_dir() {
+                    walker.skip_current_dir();
+                }
+                entries.push(WalkEntry {
+                    relative_path: relative_to(root, &path),
+                    is_dir: entry.file_type().is_dir(),
+                    path,
+                    size: 0,
+                    decision: Decision::OtherCourse(reason),
+                });
+                continue;
+            }
+        }
+
+        // Unless links are followed they are never read; a linked directory
+        // shows up here too, as its entry is the link itself
+        if !follow && entry.path_is_symlink() {
+            let decision = match fs::metadata(&path) {
+                Err(_) => Decision::Unreadable("broken symlink".to_string()),
+                Ok(_) => Decision::Symlink(link_target(&path)),
+            };
+            entries.push(WalkEntry {
+                relative_path: relative_to(root, &path),
+                is_dir: fs::metadata(&path).is_ok_and(|m| m.is_dir()),
+                decision,
+                path,
+                size: 0,
+            });
+            continue;
+        }
+
+        // A followed link must stay inside the root, or a link such as
+        // `ext -> ../../outside` would pull in files from anywhere
+        if follow && entry.path_is_symlink() && !fs::canonicalize(&path).is_ok_and(|real| real.starts_with(root)) {
+            if entry.file_type().is_dir() {
+                walker.skip_current_dir();
+            }
+            entries.push(WalkEntry {
+                relative_path: relative_to(root, &path),
+                is_dir: entry.file_type().is_dir(),
+                decision: Decision::Unreadable("link points outside the root".to_string()),
+                path,
+                size: 0,
+            });
+            continue;
+        }
+
+        // Prune excluded directories (but never where the walk starts)
+        if entry.file_type().is_dir() {
+            if entry.depth() > 0 {
+                let name = entry.file_name().to_string_lossy();
+                let decision = match config.matching_folder_pattern(&name) {
+                    Some(pattern) => Some(Decision::ExcludedFolder(pattern.to_string())),
+                    None => hidden_decision(config, &name, true),
+                };
+                if let Some(decision) = decision {
+                    walker.skip_current_dir();
+                    entries.push(WalkEntry {
+                        relative_path: relative_to(root, &path),
+                        path,
+                        is_dir: true,
+                        size: 0,
+                        decision,
+                    });
+                }
+            }
+            continue;
+        }
+
+        // Skip the output file
+        if path == output_path {
+            continue;
+        }
+
+        let extension = extension_of(&path);
+        let relative_path = relative_to(root, &path);
+
+        let metadata = entry.metadata().ok();
+        let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+        let modified = metadata.and_then(|m| m.modified().ok());
+
+        let decision = match (&git_files, hidden_decision(config, &entry.file_name().to_string_lossy(), false)) {
+            (Some(git_files), _) if !git_files.contains(&relative_path) => {
+                Decision::NotSelected(git_files.reason().to_string())
+            }
+            (_, Some(hidden)) => hidden,
+            _ if config.should_exclude(&extension) => Decision::ExcludedExtension(extension),
+            _ => modified_decision(&config.modified, modified).unwrap_or_else(|| size_decision(config, &extension, size)),
+        };
+
+        entries.push(WalkEntry {
+            relative_path,
+            path,
+            is_dir: false,
+            size,
+            decision,
+        });
+    }
+}
+
+/// Where a symbolic link points, as stored in the link
+pub fn link_target(path: &Path) -> String {
+    fs::read_link(path)
+        .map(|target| target.to_string_lossy().to_string())
+        .unwrap_or_else(|_| "?".to_string())
+}
+
+/// With links followed, one file can be reached through several paths. The
+/// path without links in it is kept (or the first in path order when every
+/// path goes through a link); the others are marked `Decision::SameFile`.
+fn mark_repeated_files(entries: &mut [WalkEntry]) {
+    let mut candidates: Vec<(usize, PathBuf)> = entries
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| !e.is_dir && matches!(e.decision, Decision::Included | Decision::Truncated(_)))
+        .filter_map(|(i, e)| fs::canonicalize(&e.path).ok().map(|real| (i, real)))
+        .collect();
+    candidates.sort_by(|(a, real_a), (b, real_b)| {
+        let via_link = |i: usize, real: &PathBuf| entries[i].path != *real;
+        via_link(*a, real_a)
+            .cmp(&via_link(*b, real_b))
+            .then_with(|| path_components(&entries[*a].relative_path).cmp(path_components(&entries[*b].relative_path)))
+    });
+
+    let mut kept: HashMap<PathBuf, usize> = HashMap::new();
+    for (index, real) in candidates {
+        match kept.get(&real) {
+            Some(&first) => entries[index].decision = Decision::SameFile(entries[first].relative_path.clone()),
+            None => {
+                kept.insert(real, index);
+            }
+        }
+    }
+}
+
+/// Leaves out OS junk files and, with `hidden = exclude`, names starting with
+/// a dot, unless an `[include]` pattern keeps the name
+pub fn hidden_decision(config: &Config, name: &str, is_dir: bool) -> Option<Decision> {
+    if config.include_rule(name).is_some() {
+        return None;
+    }
+    match config.junk_pattern(name) {
+        Some(pattern) if !is_dir => Some(Decision::Junk(pattern.to_string())),
+        _ if config.hidden == HiddenPolicy::Exclude && name.starts_with('.') => Some(Decision::Hidden),
+        _ => None,
+    }
+}
+
+/// Applies the `--newer-than` and `--older-than` cutoffs to a file's
+/// modification time; files without one are kept
+pub fn modified_decision(range: &ModifiedRange, modified: Option<SystemTime>) -> Option<Decision> {
+    let modified: DateTime<Local> = modified?.into();
+    match (range.after, range.before) {
+        (Some(after), _) if modified < after => Some(Decision::TooOld(format_cutoff(after))),
+        (_, Some(before)) if modified >= before => Some(Decision::TooNew(format_cutoff(before))),
+        _ => None,
+    }
+}
+
+/// Applies the configured size limit to an otherwise included file
+pub fn size_decision(config: &Config, extension: &str, size: u64) -> Decision {
+    match config.size_limit() {
+        Some((limit, _)) if size > limit => match config.oversize_action_for(extension) {
+            OversizeAction::Skip => Decision::TooLarge(limit),
+            OversizeAction::Truncate => Decision::Truncated(limit),
+        },
+        _ => Decision::Included,
+    }
+}
+
+/// Applies the configured BOM and line ending normalization to freshly read content
+fn normalize_content(mut content: String, config: &Config) -> String {
+    if config.strip_bom {
+        content = normalize::strip_bom(content);
+    }
+    if let Some(ending) = config.line_endings {
+        content = normalize::convert_line_endings(&content, ending);
+    }
+    content
+}
+
+/// Lowercase extension including the dot, or an empty string
+pub fn extension_of(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| format!(".{}", e.to_lowercase()))
+        .unwrap_or_default()
+}
+
+fn path_components(relative: &str) -> impl Iterator<Item = &str> {
+    relative.split(['\\', '/'])
+}
+
+/// Path as shown to users, without the Windows extended-length prefix
+pub fn display_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    path.strip_prefix(r"\\?\").unwrap_or(&path).to_string()
+}
+
+/// Store relative path from root
+fn relative_to(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.file_name().unwrap_or_default().to_string_lossy().to_string())
+}
+
+/// Heuristic binary check: a NUL byte in the first few KB
+pub fn looks_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_SNIFF_LEN)].contains(&0)
+}
+
+/// Reads just enough of `path` to decide whether it is binary
+pub fn sniff_binary(path: &Path) -> std::io::Result<bool> {
+    let mut buf = vec![0u8; BINARY_SNIFF_LEN];
+    let mut file = fs::File::open(path)?;
+    let mut filled = 0;
+    while filled < buf.len() {
+        let n = file.read(&mut buf[filled..])?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
+    }
+    Ok(looks_binary(&buf[..filled]))
+}
+
+/// Reads one selected file and applies normalization, secret detection,
+/// compression and the final newline
+fn read_file(
+    entry: &WalkEntry,
+    config: &Config,
+    detector: Option<&SecretDetector>,
+    verbose: bool,
+    progress: &ProgressBar,
+) -> Result<FileContent, Error> {
+    let (path, relative) = (&entry.path, &entry.relative_path);
+    let read = match entry.decision {
+        Decision::Truncated(limit) => {
+            read_excerpt(path, entry.size, limit).map(|text| text.map(|(text, marker)| (text, Some(marker))))
+        }
+        _ => read_text(path).map(|text| text.map(|text| (text, None))),
+    };
+    let Some((mut content, excerpt)) = read.map_err(|e| Error::io(path, e))? else {
+        if verbose {
+            progress.println(format!("Skipping binary: {}", relative));
+        }
+        return Ok(FileContent::Binary);
+    };
+    if verbose {
+        progress.println(format!("Processing: {}", relative));
+    }
+    content = normalize_content(content, config);
+    // Secrets are redacted before compression so reported line numbers match the source
+    let redactions = match detector {
+        Some(detector) => {
+            let (redacted, found) = detector.redact(path, &content);
+            if config.redact_secrets {
+                content = redacted;
+            }
+            found
+        }
+        None => Vec::new(),
+    };
+    // Excerpts are left alone: a cut-off comment or string would swallow the marker
+    let extension = extension_of(path);
+    let syntax = compress::syntax_for(&extension)
+        .filter(|_| config.compress_enabled_for(&extension))
+        .filter(|_| !matches!(entry.decision, Decision::Truncated(_)));
+    let mut compressed_saved = 0;
+    if let Some(syntax) = syntax {
+        let compressed = compress::compress(&content, syntax);
+        compressed_saved = content.len().saturating_sub(compressed.len()) as u64;
+        content = compressed;
+    }
+    if config.final_newline {
+        content = normalize::ensure_final_newline(content);
+    }
+    // A redacted secret spanning lines of the head moves the marker up, so
+    // find it again in the processed content
+    let truncation = excerpt.and_then(|(marker, omitted_lines)| {
+        content
+            .lines()
+            .position(|line| line == marker)
+            .map(|marker_line| Truncation { marker_line, omitted_lines })
+    });
+    Ok(FileContent::Text {
+        file: FileEntry {
+            relative_path: relative.clone(),
+            content,
+            duplicate_of: None,
+            truncation,
+        },
+        redactions,
+        compressed_saved,
+    })
+}
+
+/// Reads a file as UTF-8 text, returning `None` if it looks binary
+fn read_text(path: &Path) -> std::io::Result<Option<String>> {
+    let bytes = fs::read(path)?;
+    if looks_binary(&bytes) {
+        return Ok(None);
+    }
+    String::from_utf8(bytes)
+        .map(Some)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// Reads the first and last `limit / 2` bytes of a large text file, joined by
+/// a marker giving the number of bytes left out, along with the marker text
+/// and the omitted line count. Returns `None` if binary.
+fn read_excerpt(path: &Path, size: u64, limit: u64) -> std::io::Result<Option<(String, (String, usize))>> {
+    let half = (limit / 2) as usize;
+    let mut file = fs::File::open(path)?;
+
+    let mut head = vec![0u8; half];
+    file.read_exact(&mut head)?;
+    if looks_binary(&head) {
+        return Ok(None);
+    }
+
+    let mut tail = vec![0u8; half];
+    file.seek(SeekFrom::End(-(half as i64)))?;
+    file.read_exact(&mut tail)?;
+
+    // Trim partial UTF-8 sequences at the cut points
+    let head_end = match std::str::from_utf8(&head) {
+        Ok(_) => head.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(e) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+    };
+    let tail_start = tail
+        .iter()
+        .position(|&b| (b as i8) >= -0x40)
+        .unwrap_or(tail.len());
+    let head = std::str::from_utf8(&head[..head_end]).unwrap();
+    let tail = std::str::from_utf8(&tail[tail_start..])
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+    // Count the line breaks left out so line numbers stay true for the tail
+    let omitted = size - head.len() as u64 - tail.len() as u64;
+    file.seek(SeekFrom::Start(head.len() as u64))?;
+    let mut middle = (&mut file).take(omitted);
+    let mut buffer = [0u8; 64 * 1024];
+    let mut omitted_lines = 0;
+    loop {
+        let n = middle.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        omitted_lines += buffer[..n].iter().filter(|&&b| b == b'\n').count();
+    }
+
+    let marker = truncation_marker(omitted, omitted_lines);
+    let content = format!("{}\n{}\n{}", head, marker, tail);
+    Ok(Some((content, (marker, omitted_lines))))
+}
+
+/// Groups files by the first component of their relative path
+pub fn group_into_courses(files: Vec<FileEntry>) -> Vec<CourseGroup> {
     let mut course_map: BTreeMap<String, Vec<FileEntry>> = BTreeMap::new();
 
     for file in files {
         let course_name = file
             .relative_path
-            .split(|c: char| c == '\\' || c == '/')
+            .split(['\\', '/'])
             .next()
             .unwrap_or("Uncategorized")
             .to_string();
@@ -206,22 +1213,66 @@ fn group_into_courses(files: Vec<FileEntry>) -> Vec<CourseGroup> {
         .collect()
 }
 
+/// Replaces later copies of identical content with a reference to the first
+/// occurrence. Files are visited in output order so the first one kept is the
+/// first one a reader sees.
+fn dedupe_files(courses: &mut [CourseGroup], scope: DedupeScope, stats: &mut ScanStats) {
+    if scope == DedupeScope::Off {
+        return;
+    }
+
+    let mut seen: HashMap<u64, Vec<(usize, usize)>> = HashMap::new();
+    let mut duplicates: Vec<(usize, usize, String)> = Vec::new();
+
+    for (ci, course) in courses.iter().enumerate() {
+        if scope == DedupeScope::Course {
+            seen.clear();
+        }
+        for (fi, file) in course.files.iter().enumerate() {
+            if file.content.is_empty() {
+                continue;
+            }
+            let mut hasher = DefaultHasher::new();
+            file.content.hash(&mut hasher);
+            let candidates = seen.entry(hasher.finish()).or_default();
+
+            // Hash collisions are resolved by comparing the content itself
+            let original = candidates
+                .iter()
+                .map(|&(oc, of)| &courses[oc].files[of])
+                .find(|original| original.content == file.content);
+            match original {
+                Some(original) => duplicates.push((ci, fi, original.relative_path.clone())),
+                None => candidates.push((ci, fi)),
+            }
+        }
+    }
+
+    for (ci, fi, original) in duplicates {
+        let file = &mut courses[ci].files[fi];
+        stats.files_deduplicated += 1;
+        stats.bytes_saved += file.content.len() as u64;
+        file.content = String::new();
+        file.duplicate_of = Some(original);
+    }
+}
+
 #[derive(Default)]
 struct TreeNode {
     children: BTreeMap<String, TreeNode>,
     is_file: bool,
+    /// Target of a listed symbolic link
+    link_target: Option<String>,
 }
 
 impl TreeNode {
-    fn insert(&mut self, parts: &[&str]) {
-        if parts.is_empty() {
-            return;
-        }
+    fn insert(&mut self, parts: &[&str]) -> &mut TreeNode {
         let child = self.children.entry(parts[0].to_string()).or_default();
         if parts.len() == 1 {
             child.is_file = true;
+            child
         } else {
-            child.insert(&parts[1..]);
+            child.insert(&parts[1..])
         }
     }
 
@@ -233,6 +1284,10 @@ impl TreeNode {
             output.push_str(prefix);
             output.push_str(connector);
             output.push_str(name);
+            if let Some(target) = &node.link_target {
+                output.push_str(" -> ");
+                output.push_str(target);
+            }
             output.push('\n');
 
             if !node.children.is_empty() {
@@ -247,13 +1302,26 @@ impl TreeNode {
     }
 }
 
-fn build_file_list(file_paths: &[(PathBuf, String)]) -> String {
+/// Renders relative file paths as the `Directory List` tree
+pub fn build_file_list<'a>(relative_paths: impl IntoIterator<Item = &'a str>) -> String {
+    build_tree(relative_paths, [])
+}
+
+/// Like `build_file_list`, with symbolic links shown as `name -> target`
+fn build_tree<'a>(
+    relative_paths: impl IntoIterator<Item = &'a str>,
+    links: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> String {
     let mut root = TreeNode::default();
 
-    for (_, rel) in file_paths {
-        let parts: Vec<&str> = rel.split(|c| c == '\\' || c == '/').collect();
+    for relative_path in relative_paths {
+        let parts: Vec<&str> = relative_path.split(['\\', '/']).collect();
         root.insert(&parts);
     }
+    for (relative_path, target) in links {
+        let parts: Vec<&str> = relative_path.split(['\\', '/']).collect();
+        root.insert(&parts).link_target = Some(target.to_string());
+    }
 
     let mut output = String::new();
     root.render("", &mut output);
@@ -274,19 +1342,22 @@ mod tests {
     fn test_group_into_courses() {
         let files = vec![
             FileEntry {
-                absolute_path: "C:\\Courses\\Rust\\01\\notes.txt".to_string(),
                 relative_path: "Rust\\01\\notes.txt".to_string(),
                 content: "rust notes".to_string(),
+                duplicate_of: None,
+                truncation: None,
             },
             FileEntry {
-                absolute_path: "C:\\Courses\\Python\\01\\notes.txt".to_string(),
                 relative_path: "Python\\01\\notes.txt".to_string(),
                 content: "python notes".to_string(),
+                duplicate_of: None,
+                truncation: None,
             },
             FileEntry {
-                absolute_path: "C:\\Courses\\Rust\\02\\notes.txt".to_string(),
                 relative_path: "Rust\\02\\notes.txt".to_string(),
                 content: "more rust".to_string(),
+                duplicate_of: None,
+                truncation: None,
             },
         ];
 
@@ -298,4 +1369,291 @@ mod tests {
         assert_eq!(courses[1].name, "Rust");
         assert_eq!(courses[1].files.len(), 2);
     }
+
+    #[test]
+    fn test_looks_binary() {
+        assert!(!looks_binary(b"fn main() {}\n"));
+        assert!(looks_binary(b"PK\x03\x04\x00\x00"));
+        assert!(!looks_binary(b""));
+    }
+
+    fn entry(path: &str, content: &str) -> FileEntry {
+        FileEntry {
+            relative_path: path.to_string(),
+            content: content.to_string(),
+            duplicate_of: None,
+            truncation: None,
+        }
+    }
+
+    #[test]
+    fn test_dedupe_files() {
+        let files = vec![
+            entry("A\\starter.rs", "fn main() {}"),
+            entry("A\\lesson.rs", "fn main() {}"),
+            entry("B\\starter.rs", "fn main() {}"),
+            entry("B\\other.rs", "unique"),
+        ];
+
+        let mut courses = group_into_courses(files);
+        let mut stats = ScanStats::default();
+        dedupe_files(&mut courses, DedupeScope::Course, &mut stats);
+        assert_eq!(stats.files_deduplicated, 1);
+        assert_eq!(courses[0].files[1].duplicate_of.as_deref(), Some("A\\starter.rs"));
+        assert!(courses[1].files[0].duplicate_of.is_none());
+
+        let mut stats = ScanStats::default();
+        dedupe_files(&mut courses, DedupeScope::Run, &mut stats);
+        assert_eq!(stats.files_deduplicated, 1);
+        assert_eq!(stats.bytes_saved, 12);
+        assert_eq!(courses[1].files[0].duplicate_of.as_deref(), Some("A\\starter.rs"));
+        assert!(courses[1].files[1].duplicate_of.is_none());
+    }
+
+    #[test]
+    fn test_build_tree_with_links() {
+        let tree = build_tree(["C/real/a.txt"], [("C/link.txt", "real/a.txt"), ("D/alias", "../C/real")]);
+        assert_eq!(
+            tree,
+            "├───C\n│   ├───link.txt -> real/a.txt\n│   └───real\n│       └───a.txt\n└───D\n    └───alias -> ../C/real"
+        );
+    }
+
+    #[test]
+    fn test_hidden_decision() {
+        let text = "[settings]\nhidden = exclude\n\n[include]\n.github\n";
+        let config = Config::parse_str(text, "config.txt").unwrap();
+
+        assert_eq!(hidden_decision(&config, ".env", false), Some(Decision::Hidden));
+        assert_eq!(hidden_decision(&config, ".github", true), None);
+        assert_eq!(hidden_decision(&config, "thumbs.db", false), Some(Decision::Junk("Thumbs.db".to_string())));
+        assert_eq!(hidden_decision(&config, "._notes.txt", false), Some(Decision::Junk("._*".to_string())));
+        assert_eq!(hidden_decision(&config, "notes.txt", false), None);
+    }
+
+    #[test]
+    fn test_modified_decision() {
+        let day = std::time::Duration::from_secs(24 * 60 * 60);
+        let now = SystemTime::now();
+        let range = ModifiedRange::new(Some((now - 7 * day).into()), Some((now - day).into())).unwrap();
+
+        assert_eq!(modified_decision(&range, Some(now - 3 * day)), None);
+        assert!(matches!(modified_decision(&range, Some(now - 10 * day)), Some(Decision::TooOld(_))));
+        assert!(matches!(modified_decision(&range, Some(now)), Some(Decision::TooNew(_))));
+        assert_eq!(modified_decision(&range, None), None);
+        assert_eq!(modified_decision(&ModifiedRange::default(), Some(now)), None);
+    }
+
+    #[test]
+    fn test_walk_starts() {
+        let root = Path::new("/courses");
+        let only = [PathBuf::from("Rust/02"), PathBuf::from("Rust"), PathBuf::from("Go/intro.md")];
+        let starts = walk_starts(root, &only).unwrap();
+        assert_eq!(
+            starts,
+            vec![(PathBuf::from("/courses/Go/intro.md"), 2), (PathBuf::from("/courses/Rust"), 1)]
+        );
+
+        assert!(walk_starts(root, &[PathBuf::from("../etc")]).is_err());
+        assert!(walk_starts(root, &[PathBuf::from("/etc")]).is_err());
+    }
+
+    /// Fresh directory under the system temp dir, unique to this test run
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("fileassetbuilder-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root.canonicalize().unwrap()
+    }
+
+    #[test]
+    fn test_scan_cache() {
+        let root = temp_root("cache");
+        fs::create_dir_all(root.join("Rust/.git")).unwrap();
+        fs::write(root.join("Rust/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("Rust/notes.md"), "# Notes\n").unwrap();
+
+        let scanner = Scanner::new(&root);
+        let mut cache = ScanCache::default();
+        let first = scanner.scan_cached(&mut cache).unwrap();
+        assert_eq!(first.stats.files_read, 2);
+
+        // Churn in excluded paths leaves the output as it is
+        fs::write(root.join("Rust/.git/index"), "changed").unwrap();
+        fs::write(root.join("Rust/clip.mp4"), "video").unwrap();
+        let ignored = [root.join("Rust/.git/index"), root.join("Rust/clip.mp4")];
+        assert!(!scanner.is_affected_by(&mut cache, &ignored).unwrap());
+
+        fs::write(root.join("Rust/main.rs"), "fn main() { run() }\n").unwrap();
+        assert!(scanner.is_affected_by(&mut cache, &[root.join("Rust/main.rs")]).unwrap());
+        let second = scanner.scan_cached(&mut cache).unwrap();
+        assert_eq!(second.stats.files_read, 1);
+        assert_eq!(second.courses[0].files[0].content, "fn main() { run() }\n");
+
+        // A same-size edit whose event was missed is caught by the modification time
+        fs::write(root.join("Rust/notes.md"), "# Tasks\n").unwrap();
+        let file = fs::File::options().write(true).open(root.join("Rust/notes.md")).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+        let third = scanner.scan_cached(&mut cache).unwrap();
+        assert_eq!(third.stats.files_read, 1);
+        assert_eq!(third.courses[0].files[1].content, "# Tasks\n");
+
+        // A new file changes the walk even before its content is looked at
+        fs::write(root.join("Rust/lib.rs"), "").unwrap();
+        assert!(scanner.is_affected_by(&mut cache, &[root.join("Rust")]).unwrap());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_reproducible_output() {
+        use crate::config::parse_cutoff;
+        use crate::normalize::LineEnding;
+        use crate::output::{render, OutputOptions, PathStyle, Timestamp};
+
+        let (windows, unix) = (temp_root("repro-windows"), temp_root("repro-unix"));
+        fs::create_dir_all(windows.join("Rust/01")).unwrap();
+        fs::create_dir_all(unix.join("Rust/01")).unwrap();
+        fs::write(windows.join("Rust/01/main.rs"), "\u{feff}fn main() {\r\n}\r\n").unwrap();
+        fs::write(unix.join("Rust/01/main.rs"), "fn main() {\n}\n").unwrap();
+
+        // What --reproducible sets
+        let mut config = Config::with_defaults();
+        config.line_endings = Some(LineEnding::Lf);
+        config.strip_bom = true;
+        config.modified = ModifiedRange::new(parse_cutoff("2024-05-01").ok(), None).unwrap();
+        let options = OutputOptions {
+            path_style: PathStyle::Relative,
+            forward_slashes: true,
+            utc: true,
+            timestamp: Timestamp::Omit,
+            ..Default::default()
+        };
+
+        let mut from_windows = Scanner::new(&windows).config(config.clone()).scan().unwrap();
+        // As scanned on Windows
+        for file in from_windows.courses.iter_mut().flat_map(|c| c.files.iter_mut()) {
+            file.relative_path = file.relative_path.replace('/', "\\");
+        }
+        let from_unix = Scanner::new(&unix).config(config).scan().unwrap();
+
+        let (mut a, mut b) = (Vec::new(), Vec::new());
+        render(&mut a, &from_windows, &options).unwrap();
+        render(&mut b, &from_unix, &options).unwrap();
+        assert_eq!(String::from_utf8(a).unwrap(), String::from_utf8(b.clone()).unwrap());
+
+        let text = String::from_utf8(b).unwrap();
+        assert!(!text.contains(['\r', '\u{feff}', '\\']));
+        assert!(text.contains("Files modified since: 2024-"));
+        assert!(text.lines().any(|l| l.starts_with("Files modified since: ") && l.ends_with(" UTC")));
+
+        fs::remove_dir_all(&windows).unwrap();
+        fs::remove_dir_all(&unix).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_compression_savings_per_file() {
+        let root = temp_root("compressions");
+        fs::create_dir_all(root.join("Rust")).unwrap();
+        fs::write(root.join("Rust/main.rs"), "// entry point\nfn main() {}\n").unwrap();
+        fs::write(root.join("Rust/lib.rs"), "pub fn f() {}\n").unwrap();
+        fs::write(root.join("Rust/notes.md"), "// not code\n").unwrap();
+
+        let mut config = Config::with_defaults();
+        config.compress = true;
+        let result = Scanner::new(&root).config(config).scan().unwrap();
+
+        // Files compression left unchanged and unsupported languages are not listed
+        let saved: Vec<(&str, u64)> = result.compressions.iter().map(|c| (c.path.as_str(), c.bytes_saved)).collect();
+        let main = Path::new("Rust").join("main.rs");
+        assert_eq!(saved, vec![(main.to_str().unwrap(), 15)]);
+        assert_eq!(result.compressions[0].tokens_saved(), 4);
+        assert_eq!(result.stats.compressed_bytes_saved, 15);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_dangling_symlink_is_skipped() {
+        let root = temp_root("dangling");
+        fs::create_dir_all(root.join("Rust")).unwrap();
+        fs::write(root.join("Rust/main.rs"), "fn main() {}\n").unwrap();
+        std::os::unix::fs::symlink(root.join("Rust/missing.rs"), root.join("Rust/link.rs")).unwrap();
+
+        let result = Scanner::new(&root).scan().unwrap();
+        assert_eq!(result.skipped.len(), 1);
+        assert_eq!(result.skipped[0].relative_path, "Rust/link.rs");
+        assert_eq!(result.skipped[0].reason, "broken symlink");
+        assert_eq!(result.stats.entries_skipped, 1);
+        assert_eq!(result.stats.files_processed, 1);
+        assert!(result.is_incomplete());
+
+        fs::remove_file(root.join("Rust/link.rs")).unwrap();
+        assert!(!Scanner::new(&root).scan().unwrap().is_incomplete());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_only_inside_excluded_folder() {
+        let root = temp_root("only-excluded");
+        fs::create_dir_all(root.join("Rust/node_modules/pkg")).unwrap();
+        fs::create_dir_all(root.join("Rust/.drafts")).unwrap();
+        fs::write(root.join("Rust/node_modules/pkg/index.js"), "").unwrap();
+        fs::write(root.join("Rust/.drafts/idea.md"), "").unwrap();
+        fs::write(root.join("Rust/main.rs"), "").unwrap();
+
+        let mut config = Config::with_defaults();
+        config.hidden = HiddenPolicy::Exclude;
+        config.only = vec![
+            PathBuf::from("Rust/node_modules/pkg"),
+            PathBuf::from("Rust/node_modules/other"),
+            PathBuf::from("Rust/.drafts/idea.md"),
+            PathBuf::from("Rust/main.rs"),
+        ];
+        let entries = walk_entries(&root, &config, &root.join(DEFAULT_OUTPUT_FILENAME)).unwrap();
+        let decisions: Vec<(&str, &Decision)> = entries.iter().map(|e| (e.relative_path.as_str(), &e.decision)).collect();
+        assert_eq!(
+            decisions,
+            vec![
+                ("Rust/.drafts", &Decision::Hidden),
+                ("Rust/main.rs", &Decision::Included),
+                ("Rust/node_modules", &Decision::ExcludedFolder("node_modules".to_string())),
+            ]
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_depth_limits_report_entries() {
+        let root = temp_root("depth");
+        fs::create_dir_all(root.join("Rust/01/deep")).unwrap();
+        fs::write(root.join("top.md"), "").unwrap();
+        fs::write(root.join("Rust/intro.md"), "").unwrap();
+        fs::write(root.join("Rust/01/main.rs"), "").unwrap();
+        fs::write(root.join("Rust/01/deep/more.rs"), "").unwrap();
+
+        let mut config = Config::with_defaults();
+        config.min_depth = Some(2);
+        config.max_depth = Some(2);
+        let entries = walk_entries(&root, &config, &root.join("out.txt")).unwrap();
+        let decision = |relative: &str| {
+            let relative = relative.replace('/', std::path::MAIN_SEPARATOR_STR);
+            entries.iter().find(|e| e.relative_path == relative).map(|e| e.decision.clone())
+        };
+
+        assert_eq!(decision("Rust/intro.md"), Some(Decision::Included));
+        assert_eq!(decision("top.md"), Some(Decision::NotSelected("shallower than --min-depth 2".to_string())));
+        assert_eq!(decision("Rust/01/main.rs"), Some(Decision::NotSelected("deeper than --max-depth 2".to_string())));
+        assert_eq!(decision("Rust/01/deep"), Some(Decision::NotSelected("deeper than --max-depth 2".to_string())));
+        assert_eq!(decision("Rust/01/deep/more.rs"), None);
+
+        let result = Scanner::new(&root).config(config).scan().unwrap();
+        assert_eq!(result.stats.files_processed, 1);
+        assert_eq!(result.stats.files_not_selected, 3);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
 }
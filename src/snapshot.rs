@@ -0,0 +1,447 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::output::{truncated_lines, COURSE_END, COURSE_START, FILE_SEPARATOR, SEPARATOR};
+use crate::error::Error;
+use crate::scanner::{ScanResult, Truncation};
+use crate::secrets;
+
+const FILE_HEADER: &str = "File: \"";
+const DUPLICATE_PREFIX: &str = "[identical to \"";
+
+/// One file block read back from a snapshot
+pub struct SnapshotFile {
+    pub course: String,
+    /// Path exactly as written in the `File:` header
+    pub header_path: String,
+    /// Path below the scanned root, `/`-separated
+    pub relative_path: String,
+    /// Content with line numbers removed; empty for duplicates
+    pub content: String,
+    /// Relative path of the file this one was deduplicated against
+    pub duplicate_of: Option<String>,
+    /// Set when the content is a head/tail excerpt of an oversized file
+    pub truncation: Option<Truncation>,
+}
+
+impl SnapshotFile {
+    /// Whether the content holds `[REDACTED:<kind>]` placeholders where
+    /// secrets were
+    pub fn has_redactions(&self) -> bool {
+        secrets::has_placeholder(&self.content)
+    }
+}
+
+pub struct Snapshot {
+    files: Vec<SnapshotFile>,
+    /// Position in `files` by relative path, so lookups do not scan the list
+    by_path: HashMap<String, usize>,
+}
+
+impl Snapshot {
+    pub fn new(files: Vec<SnapshotFile>) -> Self {
+        let mut by_path = HashMap::with_capacity(files.len());
+        for (index, file) in files.iter().enumerate() {
+            by_path.entry(file.relative_path.clone()).or_insert(index);
+        }
+        Snapshot { files, by_path }
+    }
+
+    pub fn files(&self) -> &[SnapshotFile] {
+        &self.files
+    }
+
+    /// Snapshot of a fresh scan, as it would read back once written
+    pub fn from_scan(result: ScanResult) -> Self {
+        let files = result
+            .courses
+            .into_iter()
+            .flat_map(|course| {
+                let name = course.name;
+                course.files.into_iter().map(move |file| {
+                    let relative_path = file.relative_path.replace('\\', "/");
+                    SnapshotFile {
+                        course: name.clone(),
+                        header_path: relative_path.clone(),
+                        relative_path,
+                        truncation: file.truncation,
+                        content: file.content,
+                        duplicate_of: file.duplicate_of.map(|p| p.replace('\\', "/")),
+                    }
+                })
+            })
+            .collect();
+        Snapshot::new(files)
+    }
+
+    /// Content of the file at `relative_path`, following duplicate references
+    pub fn content_of(&self, relative_path: &str) -> Option<&str> {
+        self.resolve(relative_path).map(|file| file.content.as_str())
+    }
+
+    /// The file holding the content of `relative_path`, following duplicate
+    /// references
+    pub fn resolve(&self, relative_path: &str) -> Option<&SnapshotFile> {
+        let mut file = &self.files[*self.by_path.get(relative_path)?];
+        // A chain of references longer than the snapshot must loop
+        for _ in 0..self.files.len() {
+            match &file.duplicate_of {
+                Some(original) => file = &self.files[*self.by_path.get(original.as_str())?],
+                None => return Some(file),
+            }
+        }
+        None
+    }
+}
+
+pub fn read_snapshot(path: &Path) -> Result<Snapshot, Error> {
+    let text = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+    parse_snapshot(&text).map_err(|message| Error::InvalidSnapshot {
+        path: path.to_path_buf(),
+        message,
+    })
+}
+
+/// Parses the output of `render`, in any path style, with or without
+/// line numbers, file stats or CRLF line endings
+fn parse_snapshot(text: &str) -> Result<Snapshot, String> {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let line_starts: Vec<usize> = lines
+        .iter()
+        .scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len();
+            Some(start)
+        })
+        .collect();
+    let bare = |i: usize| lines.get(i).map(|l| l.trim_end_matches(['\r', '\n']));
+
+    let courses_start = (0..lines.len())
+        .find(|&i| bare(i) == Some(SEPARATOR) && bare(i + 1) == Some("Courses") && bare(i + 2) == Some(SEPARATOR))
+        .ok_or("not a snapshot (no Courses section)")?;
+
+    let is_file_start = |i: usize| {
+        bare(i) == Some(FILE_SEPARATOR) && bare(i + 1).is_some_and(|l| l.starts_with(FILE_HEADER))
+    };
+
+    let mut files = Vec::new();
+    let mut course: Option<String> = None;
+    let mut i = courses_start + 3;
+
+    while let Some(line) = bare(i) {
+        if line.is_empty() {
+            i += 1;
+        } else if let Some(name) = parse_marker(line, COURSE_START) {
+            course = Some(name.to_string());
+            i += 1;
+        } else if parse_marker(line, COURSE_END).is_some() {
+            course = None;
+            i += 1;
+        } else if line == SEPARATOR && course.is_none() {
+            // A trailing section such as "Skipped entries"
+            break;
+        } else if is_file_start(i) {
+            let course = course
+                .clone()
+                .ok_or_else(|| format!("line {}: file outside of a course", i + 2))?;
+            let (header_path, length) = parse_file_header(bare(i + 1).unwrap())
+                .ok_or_else(|| format!("line {}: malformed File header", i + 2))?;
+            i += 2;
+            // Optional metadata lines: git commit, then file stats
+            while bare(i).is_some_and(|l| l.starts_with("Commit: ") || l.starts_with("Lines: ")) {
+                i += 1;
+            }
+            if bare(i) != Some(FILE_SEPARATOR) {
+                return Err(format!("line {}: expected {} after File header", i + 1, FILE_SEPARATOR));
+            }
+            i += 1;
+
+            let content = match length {
+                // Length-prefixed blocks may contain delimiter lines, so the
+                // content is taken by size and is never a duplicate reference
+                Some(length) => {
+                    let start = line_starts.get(i).copied().unwrap_or(text.len());
+                    let end = start + length;
+                    let content = text
+                        .get(start..end)
+                        .ok_or_else(|| format!("line {}: content shorter than {} bytes", i + 1, length))?;
+                    let after = text[end..]
+                        .strip_prefix("\r\n")
+                        .or_else(|| text[end..].strip_prefix('\n'))
+                        .ok_or_else(|| format!("line {}: no line break after {} bytes of content", i + 1, length))?;
+                    i = line_starts
+                        .binary_search(&(text.len() - after.len()))
+                        .unwrap_or(lines.len());
+                    content.to_string()
+                }
+                None => {
+                    let content_start = i;
+                    while i < lines.len() && !is_file_start(i) && parse_marker(bare(i).unwrap(), COURSE_END).is_none() {
+                        i += 1;
+                    }
+
+                    // The writer ends each block with the content's own line
+                    // break plus one blank line
+                    let raw = lines[content_start..i].concat();
+                    strip_line_break(strip_line_break(&raw)).to_string()
+                }
+            };
+
+            let duplicate_of = match content.lines().collect::<Vec<_>>().as_slice() {
+                [single] if length.is_none() => parse_quoted(single, DUPLICATE_PREFIX)
+                    .filter(|_| single.ends_with("\"]"))
+                    .map(str::to_string),
+                _ => None,
+            };
+            // Numbered snapshots locate the marker exactly when line numbers are stripped
+            let truncation = content.lines().enumerate().find_map(|(marker_line, line)| {
+                truncated_lines(line).map(|omitted_lines| Truncation { marker_line, omitted_lines })
+            });
+
+            files.push(SnapshotFile {
+                course,
+                header_path,
+                relative_path: String::new(),
+                content: if duplicate_of.is_some() { String::new() } else { content },
+                duplicate_of,
+                truncation,
+            });
+        } else {
+            return Err(format!("line {}: unexpected content {:?}", i + 1, line));
+        }
+    }
+
+    resolve_relative_paths(&mut files)?;
+    strip_all_line_numbers(&mut files);
+
+    Ok(Snapshot::new(files))
+}
+
+/// Name from a `===[ COURSE: name ]====` style marker
+fn parse_marker<'a>(line: &'a str, prefix: &str) -> Option<&'a str> {
+    line.strip_prefix(prefix)?
+        .trim_end_matches('=')
+        .strip_suffix(" ]")
+}
+
+/// Path and optional `(N bytes)` content length from a `File:` header line
+fn parse_file_header(line: &str) -> Option<(String, Option<usize>)> {
+    let path = parse_quoted(line, FILE_HEADER)?;
+    let suffix = &line[FILE_HEADER.len() + path.len() + 1..];
+    let length = match suffix {
+        "" => None,
+        _ => Some(suffix.strip_prefix(" (")?.strip_suffix(" bytes)")?.parse().ok()?),
+    };
+    Some((path.to_string(), length))
+}
+
+/// Text between `prefix` (which ends with a quote) and the last quote on the line
+fn parse_quoted<'a>(line: &'a str, prefix: &str) -> Option<&'a str> {
+    let rest = line.strip_prefix(prefix)?;
+    rest.rfind('"').map(|end| &rest[..end])
+}
+
+fn strip_line_break(s: &str) -> &str {
+    s.strip_suffix("\r\n")
+        .or_else(|| s.strip_suffix('\n'))
+        .unwrap_or(s)
+}
+
+/// Turns header paths back into root-relative paths. A file's relative path
+/// starts with its course name, so the root is the prefix before that name;
+/// the prefix must be the same for every file in the snapshot.
+fn resolve_relative_paths(files: &mut [SnapshotFile]) -> Result<(), String> {
+    let mut common: Option<BTreeSet<(usize, String)>> = None;
+    for file in files.iter() {
+        let components: Vec<&str> = file.header_path.split(['/', '\\']).collect();
+        let candidates: BTreeSet<(usize, String)> = (0..components.len())
+            .filter(|&i| components[i] == file.course)
+            .map(|i| (i, components[..i].join("/")))
+            .collect();
+        common = Some(match common {
+            Some(common) => common.intersection(&candidates).cloned().collect(),
+            None => candidates,
+        });
+    }
+
+    let Some(common) = common else {
+        return Ok(());
+    };
+    // When several prefixes fit, the longest one (shortest relative paths) wins
+    let (root_len, _) = common
+        .last()
+        .cloned()
+        .ok_or("could not determine the snapshot root from the file paths")?;
+
+    let relative = |path: &str| {
+        path.split(['/', '\\'])
+            .skip(root_len)
+            .collect::<Vec<_>>()
+            .join("/")
+    };
+    for file in files.iter_mut() {
+        file.relative_path = relative(&file.header_path);
+        file.duplicate_of = file.duplicate_of.as_deref().map(relative);
+    }
+
+    Ok(())
+}
+
+/// Removes `N | ` prefixes when every non-duplicate file in the snapshot is
+/// numbered 1..n, so files that merely look numbered are left alone
+fn strip_all_line_numbers(files: &mut [SnapshotFile]) {
+    let mut stripped = Vec::new();
+    for file in files.iter().filter(|f| f.duplicate_of.is_none()) {
+        match strip_line_numbers(&file.content) {
+            Some(content) => stripped.push(content),
+            None => return,
+        }
+    }
+    if stripped.iter().all(|(c, _)| c.is_empty()) {
+        return;
+    }
+
+    let mut stripped = stripped.into_iter();
+    for file in files.iter_mut().filter(|f| f.duplicate_of.is_none()) {
+        (file.content, file.truncation) = stripped.next().unwrap();
+    }
+}
+
+/// Content without line numbers, keeping each line's own line break, and the
+/// position of the one unnumbered line, which must be a truncation marker
+fn strip_line_numbers(content: &str) -> Option<(String, Option<Truncation>)> {
+    let mut stripped = String::with_capacity(content.len());
+    let mut truncation = None;
+    let mut expected = 1;
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        let bare = line.trim_end_matches(['\r', '\n']);
+        let ending = &line[bare.len()..];
+        // Truncation markers are unnumbered and skip the omitted lines
+        if let Some(omitted_lines) = truncated_lines(bare).filter(|_| truncation.is_none()) {
+            stripped.push_str(line);
+            truncation = Some(Truncation { marker_line: i, omitted_lines });
+            expected = (expected + omitted_lines).saturating_sub(1);
+            continue;
+        }
+        let (number, rest) = bare.trim_start().split_once(" |")?;
+        if number.parse::<usize>().ok()? != expected {
+            return None;
+        }
+        stripped.push_str(rest.strip_prefix(' ').unwrap_or(rest));
+        stripped.push_str(ending);
+        expected += 1;
+    }
+    Some((stripped, truncation))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ModifiedRange;
+    use crate::normalize::LineEnding;
+    use crate::output::{render, OutputOptions, PathStyle};
+    use crate::scanner::{CourseGroup, FileEntry, ScanResult, ScanStats, SkippedEntry};
+
+    /// Looks like the end of its own block followed by another file
+    const NOTES: &str = "# Notes\n\n================\nFile: \"fake.rs\"\n================\n\n===[ END COURSE: notes.md ]===\nno newline";
+
+    /// Head and tail of an oversized file, with lines 2 to 6 left out
+    const EXCERPT: &str = "line 1\n[... truncated 30 bytes, 6 lines ...]\nline 7\n";
+
+    fn sample() -> ScanResult {
+        let file = |path: &str, content: &str, duplicate_of: Option<&str>| FileEntry {
+            relative_path: path.to_string(),
+            content: content.to_string(),
+            duplicate_of: duplicate_of.map(str::to_string),
+            truncation: None,
+        };
+        let excerpt = FileEntry {
+            truncation: Some(Truncation { marker_line: 1, omitted_lines: 6 }),
+            ..file("Rust/big.rs", EXCERPT, None)
+        };
+        ScanResult {
+            root: "/home/me/Rust".to_string(),
+            courses: vec![
+                CourseGroup {
+                    name: "Rust".to_string(),
+                    files: vec![
+                        file("Rust/main.rs", "fn main() {}\n\n", None),
+                        file("Rust/copy.rs", "", Some("Rust/main.rs")),
+                        excerpt,
+                    ],
+                },
+                CourseGroup {
+                    name: "notes.md".to_string(),
+                    files: vec![file("notes.md", NOTES, None)],
+                },
+                CourseGroup {
+                    name: "ref.txt".to_string(),
+                    files: vec![file("ref.txt", "[identical to \"Rust/main.rs\"]", None)],
+                },
+            ],
+            file_list: "Rust\n└── main.rs\n".to_string(),
+            redactions: Vec::new(),
+            compressions: Vec::new(),
+            read_errors: Vec::new(),
+            skipped: vec![SkippedEntry {
+                relative_path: "Rust/private".to_string(),
+                is_dir: true,
+                reason: "Permission denied (os error 13)".to_string(),
+            }],
+            modified: ModifiedRange::default(),
+            git: None,
+            stats: ScanStats::default(),
+        }
+    }
+
+    #[test]
+    fn test_parse_snapshot_round_trip() {
+        let variants = [
+            (PathStyle::Absolute, false, LineEnding::Lf),
+            (PathStyle::RootPrefixed, true, LineEnding::Lf),
+            (PathStyle::Relative, false, LineEnding::Crlf),
+            (PathStyle::Relative, true, LineEnding::Crlf),
+        ];
+        for (path_style, line_numbers, line_ending) in variants {
+            let options = OutputOptions {
+                path_style,
+                line_numbers,
+                line_ending,
+                file_stats: true,
+                forward_slashes: true,
+                skipped_entries: true,
+                ..Default::default()
+            };
+            let mut text = Vec::new();
+            render(&mut text, &sample(), &options).unwrap();
+            let snapshot = parse_snapshot(&String::from_utf8(text).unwrap()).unwrap();
+
+            let paths: Vec<&str> = snapshot.files().iter().map(|f| f.relative_path.as_str()).collect();
+            assert_eq!(paths, vec!["Rust/main.rs", "Rust/copy.rs", "Rust/big.rs", "notes.md", "ref.txt"]);
+            assert_eq!(snapshot.content_of("Rust/copy.rs"), snapshot.content_of("Rust/main.rs"));
+
+            let as_written = |s: &str| match line_ending {
+                LineEnding::Lf => s.to_string(),
+                LineEnding::Crlf => s.replace('\n', "\r\n"),
+            };
+            assert_eq!(snapshot.content_of("Rust/main.rs"), Some(as_written("fn main() {}\n\n").as_str()));
+            assert_eq!(snapshot.content_of("notes.md"), Some(as_written(NOTES).as_str()));
+            assert_eq!(snapshot.content_of("ref.txt"), Some("[identical to \"Rust/main.rs\"]"));
+            assert_eq!(snapshot.content_of("Rust/big.rs"), Some(as_written(EXCERPT).as_str()));
+            assert_eq!(
+                snapshot.resolve("Rust/big.rs").unwrap().truncation,
+                Some(Truncation { marker_line: 1, omitted_lines: 6 })
+            );
+        }
+    }
+
+    #[test]
+    fn test_strip_line_numbers_across_truncation() {
+        let numbered = "1 | head\n[... truncated 40 bytes, 4 lines ...]\n5 | tail\n";
+        let (content, truncation) = strip_line_numbers(numbered).unwrap();
+        assert_eq!(content, "head\n[... truncated 40 bytes, 4 lines ...]\ntail\n");
+        assert_eq!(truncation, Some(Truncation { marker_line: 1, omitted_lines: 4 }));
+        assert_eq!(strip_line_numbers("1 | head\n3 | tail"), None);
+    }
+}
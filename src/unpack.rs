@@ -0,0 +1,266 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use fileassetbuilder::{read_snapshot, Error, Snapshot};
+
+#[derive(Default)]
+pub struct UnpackReport {
+    pub written: Vec<String>,
+    pub unchanged: Vec<String>,
+    /// Existing files with different content, left untouched
+    pub conflicts: Vec<String>,
+    /// Paths that were not written, with the reason
+    pub refused: Vec<(String, String)>,
+    /// Files restored from a head/tail excerpt rather than their full content
+    pub truncated: Vec<String>,
+    /// Files restored with `[REDACTED:<kind>]` placeholders where secrets were
+    pub redacted: Vec<String>,
+}
+
+/// Recreates every file in `snapshot` below `target`. Existing files with
+/// different content are reported as conflicts and only replaced when
+/// `overwrite` is set; paths that would land outside `target` are refused.
+pub fn unpack(snapshot: &Snapshot, target: &Path, overwrite: bool) -> Result<UnpackReport, Error> {
+    let io = |source| Error::Io {
+        path: target.to_path_buf(),
+        source,
+    };
+    fs::create_dir_all(target).map_err(io)?;
+    let target = target.canonicalize().map_err(io)?;
+
+    let mut report = UnpackReport::default();
+
+    for file in snapshot.files() {
+        let path = file.relative_path.clone();
+        let Some(content) = snapshot.content_of(&file.relative_path) else {
+            let original = file.duplicate_of.as_deref().unwrap_or_default();
+            report.refused.push((path, format!("original {:?} is not in the snapshot", original)));
+            continue;
+        };
+
+        match write_file(&target, &file.relative_path, content, overwrite) {
+            Ok(Outcome::Written) => {
+                if file.truncation.is_some() {
+                    report.truncated.push(path.clone());
+                }
+                if snapshot.resolve(&file.relative_path).is_some_and(|f| f.has_redactions()) {
+                    report.redacted.push(path.clone());
+                }
+                report.written.push(path);
+            }
+            Ok(Outcome::Unchanged) => report.unchanged.push(path),
+            Ok(Outcome::Conflict) => report.conflicts.push(path),
+            Err(reason) => report.refused.push((path, reason)),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Runs the `unpack` command and prints its report. Exits the process on
+/// errors, conflicts and refused paths.
+pub fn run(snapshot_path: &Path, into: &Path, force: bool) {
+    let report = match read_snapshot(snapshot_path).and_then(|s| unpack(&s, into, force)) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    println!("Unpacked {:?} into {:?}", snapshot_path, into);
+    println!("- Files written: {}", report.written.len());
+    println!("- Files unchanged: {}", report.unchanged.len());
+    if !report.truncated.is_empty() {
+        println!("- Restored from truncated excerpts: {}", report.truncated.len());
+        for path in &report.truncated {
+            println!("    {}", path);
+        }
+    }
+    if !report.redacted.is_empty() {
+        println!("- Restored with redacted secrets: {}", report.redacted.len());
+        for path in &report.redacted {
+            println!("    {}", path);
+        }
+    }
+    if !report.conflicts.is_empty() {
+        println!("- Conflicts (existing content differs, use --force to overwrite): {}", report.conflicts.len());
+        for path in &report.conflicts {
+            println!("    {}", path);
+        }
+    }
+    if !report.refused.is_empty() {
+        println!("- Refused: {}", report.refused.len());
+        for (path, reason) in &report.refused {
+            println!("    {}: {}", path, reason);
+        }
+    }
+
+    if !report.conflicts.is_empty() || !report.refused.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+enum Outcome {
+    Written,
+    Unchanged,
+    Conflict,
+}
+
+fn write_file(target: &Path, relative: &str, content: &str, overwrite: bool) -> Result<Outcome, String> {
+    let relative = safe_relative_path(relative)?;
+    let destination = target.join(&relative);
+    if let Some(parent) = relative.parent() {
+        create_parents(target, parent)?;
+    }
+
+    match fs::symlink_metadata(&destination) {
+        Ok(meta) if meta.file_type().is_symlink() => return Err("destination is a symlink".to_string()),
+        Ok(meta) if meta.is_dir() => return Ok(Outcome::Conflict),
+        Ok(_) => {
+            let existing = fs::read(&destination).map_err(|e| format!("failed to read existing file: {}", e))?;
+            if existing == content.as_bytes() {
+                return Ok(Outcome::Unchanged);
+            }
+            if !overwrite {
+                return Ok(Outcome::Conflict);
+            }
+        }
+        Err(_) => {}
+    }
+
+    fs::write(&destination, content).map_err(|e| format!("failed to write: {}", e))?;
+    Ok(Outcome::Written)
+}
+
+/// Creates each directory of `parent` below `target` in turn, refusing to go
+/// through a symlink that already exists there, as it could point outside
+/// the target
+fn create_parents(target: &Path, parent: &Path) -> Result<(), String> {
+    let mut current = target.to_path_buf();
+    for component in parent.components() {
+        current.push(component);
+        match fs::symlink_metadata(&current) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                return Err(format!("{:?} is a symlink", current.strip_prefix(target).unwrap_or(&current)));
+            }
+            Ok(meta) if meta.is_dir() => {}
+            Ok(_) => return Err(format!("{:?} is not a directory", current.strip_prefix(target).unwrap_or(&current))),
+            Err(_) => fs::create_dir(&current).map_err(|e| format!("failed to create directory: {}", e))?,
+        }
+    }
+    Ok(())
+}
+
+/// Rejects absolute paths, drive prefixes and `.`/`..` components. Drive
+/// prefixes are refused on every platform, as a snapshot can come from any.
+fn safe_relative_path(relative: &str) -> Result<PathBuf, String> {
+    let mut path = PathBuf::new();
+    for part in relative.split('/') {
+        let drive = matches!(part.as_bytes(), [letter, b':', ..] if letter.is_ascii_alphabetic());
+        if part.is_empty() || part == "." || part == ".." || drive {
+            return Err("path escapes the target directory".to_string());
+        }
+        let mut components = Path::new(part).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(name)), None) => path.push(name),
+            _ => return Err("path escapes the target directory".to_string()),
+        }
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fileassetbuilder::SnapshotFile;
+
+    /// Fresh directory under the system temp dir, unique to this test run
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("fileassetbuilder-unpack-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root.canonicalize().unwrap()
+    }
+
+    fn snapshot(files: &[(&str, &str)]) -> Snapshot {
+        Snapshot::new(
+            files
+                .iter()
+                .map(|&(path, content)| SnapshotFile {
+                    course: path.split('/').next().unwrap().to_string(),
+                    header_path: path.to_string(),
+                    relative_path: path.to_string(),
+                    content: content.to_string(),
+                    duplicate_of: None,
+                    truncation: None,
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_safe_relative_path() {
+        assert_eq!(safe_relative_path("Rust/01/main.rs"), Ok(PathBuf::from("Rust/01/main.rs")));
+        let escaping = ["../outside.rs", "Rust/../../outside.rs", "/etc/passwd", "C:/Windows/x.rs", "C:x.rs", "./main.rs"];
+        for path in escaping {
+            assert!(safe_relative_path(path).is_err(), "{} was accepted", path);
+        }
+    }
+
+    #[test]
+    fn test_unpack_conflicts_and_force() {
+        let root = temp_root("force");
+        let target = root.join("out");
+        let files = snapshot(&[
+            ("Rust/main.rs", "fn main() {}\n"),
+            ("Rust/.env", "TOKEN=[REDACTED:dotenv]\n"),
+        ]);
+
+        let first = unpack(&files, &target, false).unwrap();
+        assert_eq!(first.written, vec!["Rust/main.rs", "Rust/.env"]);
+        assert_eq!(first.redacted, vec!["Rust/.env"]);
+
+        fs::write(target.join("Rust/main.rs"), "fn main() { edited() }\n").unwrap();
+        let second = unpack(&files, &target, false).unwrap();
+        assert_eq!(second.conflicts, vec!["Rust/main.rs"]);
+        assert_eq!(second.unchanged, vec!["Rust/.env"]);
+        assert_eq!(fs::read_to_string(target.join("Rust/main.rs")).unwrap(), "fn main() { edited() }\n");
+
+        let forced = unpack(&files, &target, true).unwrap();
+        assert_eq!(forced.written, vec!["Rust/main.rs"]);
+        assert!(forced.conflicts.is_empty());
+        assert_eq!(fs::read_to_string(target.join("Rust/main.rs")).unwrap(), "fn main() {}\n");
+
+        let escaping = unpack(&snapshot(&[("../escaped.rs", "oops\n")]), &target, true).unwrap();
+        assert_eq!(escaping.refused.len(), 1);
+        assert!(!root.join("escaped.rs").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unpack_refuses_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let root = temp_root("symlinks");
+        let (target, outside) = (root.join("out"), root.join("outside"));
+        fs::create_dir_all(&target).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        symlink(&outside, target.join("Rust")).unwrap();
+        symlink(outside.join("notes.md"), target.join("notes.md")).unwrap();
+
+        let files = snapshot(&[("Rust/main.rs", "fn main() {}\n"), ("notes.md", "# Notes\n")]);
+        let report = unpack(&files, &target, true).unwrap();
+        let refused: Vec<(&str, &str)> = report.refused.iter().map(|(p, r)| (p.as_str(), r.as_str())).collect();
+        assert_eq!(
+            refused,
+            vec![("Rust/main.rs", "\"Rust\" is a symlink"), ("notes.md", "destination is a symlink")]
+        );
+        assert!(report.written.is_empty());
+        assert_eq!(fs::read_dir(&outside).unwrap().count(), 0);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
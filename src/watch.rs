@@ -0,0 +1,156 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use fileassetbuilder::{write_output, Config, OutputOptions, ScanCache, Scanner};
+
+use crate::Args;
+
+/// Quiet period after the last event before a rebuild starts
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Watches `roots` recursively and calls `rebuild` with a root and its
+/// changed paths once a burst of events settles, or with `None` when events
+/// under the root were lost and anything may have changed. Whether a change
+/// matters is left to `rebuild`; only reads and the output file itself are
+/// ignored here. Only returns if the watcher fails.
+pub fn watch(
+    roots: &[PathBuf],
+    output_filename: &str,
+    mut rebuild: impl FnMut(&Path, Option<&[PathBuf]>),
+) -> Result<(), String> {
+    let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(tx).map_err(|e| format!("Failed to start watcher: {}", e))?;
+    for root in roots {
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch {:?}: {}", root, e))?;
+    }
+
+    let stopped = || "File watcher stopped unexpectedly".to_string();
+    loop {
+        // `None` marks a root whose events were lost
+        let mut pending: BTreeMap<usize, Option<BTreeSet<PathBuf>>> = BTreeMap::new();
+        let mut next = rx.recv().map_err(|_| stopped())?;
+        loop {
+            match next {
+                Ok(event) if needs_rescan(&event) => {
+                    for (index, root) in roots.iter().enumerate() {
+                        if event.paths.is_empty() || event.paths.iter().any(|p| p.starts_with(root)) {
+                            pending.insert(index, None);
+                        }
+                    }
+                }
+                Ok(event) => {
+                    for (index, path) in changed_paths(&event, roots, output_filename) {
+                        if let Some(paths) = pending.entry(index).or_insert_with(|| Some(BTreeSet::new())) {
+                            paths.insert(path);
+                        }
+                    }
+                }
+                Err(e) => eprintln!("Warning: File watcher error: {}", e),
+            }
+            next = match rx.recv_timeout(DEBOUNCE) {
+                Ok(next) => next,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return Err(stopped()),
+            };
+        }
+
+        for (index, paths) in pending {
+            let paths: Option<Vec<PathBuf>> = paths.map(|paths| paths.into_iter().collect());
+            rebuild(&roots[index], paths.as_deref());
+        }
+    }
+}
+
+/// Whether the watcher says it lost events, such as when the inotify queue
+/// overflows; notify reports those as `Other` events flagged for a rescan
+fn needs_rescan(event: &Event) -> bool {
+    event.need_rescan() || event.kind == EventKind::Other
+}
+
+/// Paths an event changed, with the index of the root they are under. Reads
+/// are ignored, otherwise every rebuild would trigger the next one.
+fn changed_paths(event: &Event, roots: &[PathBuf], output_filename: &str) -> Vec<(usize, PathBuf)> {
+    if matches!(event.kind, EventKind::Access(_)) {
+        return Vec::new();
+    }
+
+    let mut changed = Vec::new();
+    for path in &event.paths {
+        let root = roots.iter().position(|root| path.starts_with(root));
+        if let Some(index) = root {
+            if *path != roots[index].join(output_filename) {
+                changed.push((index, path.clone()));
+            }
+        }
+    }
+    changed
+}
+
+/// Regenerates the output for one input directory in watch mode after
+/// `changed` paths changed, printing a single summary line. Changes the scan
+/// would not see are ignored, and unchanged files come from `cache`. With no
+/// list of changes everything is read again.
+pub fn rebuild(
+    input_dir: &Path,
+    changed: Option<&[PathBuf]>,
+    cache: &mut ScanCache,
+    config: &Config,
+    args: &Args,
+    options: &OutputOptions,
+) {
+    let started = Instant::now();
+    let time = Local::now().format("%H:%M:%S");
+    let output_path = input_dir.join(&args.output);
+
+    let scanner = Scanner::new(input_dir)
+        .config(config.clone())
+        .output_filename(&args.output)
+        .detect_secrets(args.fail_on_secrets);
+
+    match changed.map(|changed| scanner.is_affected_by(cache, changed)) {
+        Some(Ok(true)) => {}
+        Some(Ok(false)) => return,
+        Some(Err(e)) => {
+            eprintln!("[{}] Error scanning {:?}: {}", time, input_dir, e);
+            return;
+        }
+        // Lost events may hide any change, so nothing cached is trusted
+        None => *cache = ScanCache::default(),
+    }
+    let result = match scanner.scan_cached(cache) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("[{}] Error scanning {:?}: {}", time, input_dir, e);
+            return;
+        }
+    };
+    if args.strict && result.is_incomplete() {
+        eprintln!("[{}] Some entries could not be read; not writing {:?} (--strict)", time, output_path);
+        return;
+    }
+    if args.fail_on_secrets && !result.redactions.is_empty() {
+        eprintln!("[{}] Secrets detected; not writing {:?} (--fail-on-secrets)", time, output_path);
+        return;
+    }
+    if let Err(e) = write_output(&output_path, &result, options) {
+        eprintln!("[{}] Error: {}", time, e);
+        return;
+    }
+
+    println!(
+        "[{}] Rebuilt {:?}: {} files ({} read again), {} secrets redacted, {} ms",
+        time,
+        output_path,
+        result.stats.files_processed,
+        result.stats.files_read,
+        result.stats.secrets_redacted,
+        started.elapsed().as_millis()
+    );
+}